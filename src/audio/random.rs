@@ -9,8 +9,8 @@ impl RandomAudio {
         Self(audios)
     }
 
-    pub fn pick(&self) -> Option<&Handle<AudioSource>> {
-        self.0.choose(&mut thread_rng())
+    pub fn pick(&self, rng: &mut impl Rng) -> Option<&Handle<AudioSource>> {
+        self.0.choose(rng)
     }
 }
 
@@ -1,13 +1,16 @@
 use super::path::Path;
 use crate::{
     audio::random::{get_audio_handles, RandomAudio},
-    characters::player::player::Player,
+    characters::{facing::Facing, occupancy::Occupancy, player::player::Player},
     combat::{
         combat::{Combat, CombatReplica, CombatState, Health},
         death::DeathSound,
         pain::PainSound,
+        weapon::{Weapon, CULTIST_WEAPON},
     },
-    tick::tick::TickEvent,
+    dungeon::dungeon::{Dungeon, FloorEntity},
+    rng::rng::GameRng,
+    tick::tick::{Energy, Speed, TurnEvent},
     visuals::{
         animation::{AnimationInfo, AnimationTimer, Animations},
         billboard::Billboard,
@@ -17,11 +20,33 @@ use crate::{
 use bevy::prelude::*;
 use bevy_sprite3d::{Sprite3dBuilder, Sprite3dParams};
 use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
-#[require(WorldLocation, Path, Health, Combat)]
+#[require(WorldLocation, Path, Health, Combat, Speed)]
 pub struct Enemy;
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct EnemyStats {
+    /// Energy gained every tick, see [`Speed`].
+    pub speed: u32,
+}
+
+impl Default for EnemyStats {
+    fn default() -> Self {
+        EnemyStats {
+            speed: Speed::default().0,
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct EnemyConfig {
+    pub cultist: EnemyStats,
+}
+
 pub fn setup_enemy_atlas(
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
     mut animations: ResMut<Animations>,
@@ -89,34 +114,82 @@ pub fn setup_enemies(
     asset_server: Res<AssetServer>,
     mut sprite_params: Sprite3dParams,
     animations: Res<Animations>,
+    mut rng: ResMut<GameRng>,
+    dungeon: Res<Dungeon>,
+    config: Res<EnemyConfig>,
+    mut occupancy: ResMut<Occupancy>,
 ) {
-    let dirs = vec![IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y];
-    for room in world
-        .0
-        .iter()
-        .collect::<Vec<_>>()
-        .choose_multiple(&mut rand::thread_rng(), 4)
-    {
-        let room = **room;
-        let face = dirs.choose(&mut rand::thread_rng()).unwrap();
-
-        let (_, layout, _) = animations.atlases.get(&"Cultist".to_string()).unwrap();
-
-        let texture_atlas = TextureAtlas {
-            index: 0,
-            layout: layout.clone(),
-        };
-        let mut replicas = Vec::<Handle<AudioSource>>::default();
-
-        for n in 1..=3 {
-            replicas.push(
-                asset_server
-                    .load::<AudioSource>(format!("sounds/characters/cultist/replica_{n}.wav")),
+    // Enemies of a floor we've already been to stay where they were left.
+    if let Some(floor) = dungeon.current_floor() {
+        for enemy in floor.enemies.iter() {
+            let entity = spawn_cultist(
+                &mut commands,
+                &asset_server,
+                &mut sprite_params,
+                &animations,
+                WorldLocation::new(enemy.location, enemy.forward),
+                Health(enemy.health),
+                enemy.speed,
+                enemy.energy,
+                enemy.weapon.clone(),
             );
+            occupancy.set(entity, enemy.location);
         }
+        return;
+    }
+
+    for room in world.spawns() {
+        let room = *room;
+        let face = Facing::ALL.choose(rng.as_mut()).unwrap();
+
+        let entity = spawn_cultist(
+            &mut commands,
+            &asset_server,
+            &mut sprite_params,
+            &animations,
+            WorldLocation::new(room, *face),
+            Health::default(),
+            Speed(config.cultist.speed),
+            Energy::default(),
+            Weapon::new(asset_server.load(CULTIST_WEAPON)),
+        );
+        occupancy.set(entity, room);
+    }
+}
+
+fn spawn_cultist(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    sprite_params: &mut Sprite3dParams,
+    animations: &Animations,
+    location: WorldLocation,
+    health: Health,
+    speed: Speed,
+    energy: Energy,
+    weapon: Weapon,
+) -> Entity {
+    let (_, layout, _) = animations.atlases.get(&"Cultist".to_string()).unwrap();
 
-        commands.spawn((
+    let texture_atlas = TextureAtlas {
+        index: 0,
+        layout: layout.clone(),
+    };
+    let mut replicas = Vec::<Handle<AudioSource>>::default();
+
+    for n in 1..=3 {
+        replicas.push(
+            asset_server.load::<AudioSource>(format!("sounds/characters/cultist/replica_{n}.wav")),
+        );
+    }
+
+    commands
+        .spawn((
             Enemy,
+            FloorEntity,
+            health,
+            speed,
+            energy,
+            weapon,
             CombatReplica(RandomAudio::new(replicas)),
             AnimationTimer {
                 timer: Timer::from_seconds(0.3, TimerMode::Repeating),
@@ -141,7 +214,7 @@ pub fn setup_enemies(
                 ],
             ))),
             Billboard,
-            WorldLocation::new(room, *face),
+            location,
             Sprite3dBuilder {
                 image: asset_server.load("sprites/cultist.png"),
                 pixels_per_metre: 64.0,
@@ -149,41 +222,51 @@ pub fn setup_enemies(
                 unlit: true,
                 ..default()
             }
-            .bundle_with_atlas(&mut sprite_params, texture_atlas),
-        ));
-    }
+            .bundle_with_atlas(sprite_params, texture_atlas),
+        ))
+        .id()
 }
 
 pub fn enemies_find_player(
     mut q_enemies: Query<(&WorldLocation, &mut Path), With<Enemy>>,
-    mut ev_tick: EventReader<TickEvent>,
+    mut ev_turn: EventReader<TurnEvent>,
     q_player: Query<&WorldLocation, With<Player>>,
     world: Res<WorldCatacomb>,
+    occupancy: Res<Occupancy>,
 ) {
     let player_location = q_player.single();
-    for _ in ev_tick.read() {
-        for (location, mut path) in q_enemies.iter_mut() {
-            if path.has_path() {
-                continue;
-            }
-            path.find_path(&location, &world, player_location.get_location());
+    for turn in ev_turn.read() {
+        let Ok((location, mut path)) = q_enemies.get_mut(turn.actor) else {
+            continue;
+        };
+        if path.has_path() {
+            continue;
         }
+        path.find_path(
+            &location,
+            &world,
+            &occupancy,
+            player_location.get_location(),
+        );
     }
 }
 
 pub fn move_enemies(
     mut q_enemies: Query<(&mut WorldLocation, &mut Path), With<Enemy>>,
-    mut ev_tick: EventReader<TickEvent>,
+    mut ev_turn: EventReader<TurnEvent>,
     combat_state: Res<CombatState>,
     world: Res<WorldCatacomb>,
+    mut occupancy: ResMut<Occupancy>,
 ) {
-    if combat_state.opponent.is_some() {
-        return;
-    }
-
-    for _ in ev_tick.read() {
-        for (mut location, mut path) in q_enemies.iter_mut() {
-            path.move_location(&mut location, &world);
+    for turn in ev_turn.read() {
+        // Turns taken during a fight are used up by it, so none are left over for afterwards.
+        if combat_state.opponent.is_some() {
+            continue;
         }
+        let Ok((mut location, mut path)) = q_enemies.get_mut(turn.actor) else {
+            continue;
+        };
+        path.move_location(&mut location, &world, &occupancy);
+        occupancy.set(turn.actor, location.get_location());
     }
 }
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::characters::location::Turn;
+use crate::characters::{location::Turn, occupancy::Occupancy};
 use crate::{characters::location::WorldLocation, WorldCatacomb};
 use pathfinding::prelude::*;
 
@@ -12,7 +12,16 @@ impl Path {
         !self.0.is_empty()
     }
 
-    pub fn move_location(&mut self, location: &mut WorldLocation, world: &Res<WorldCatacomb>) {
+    pub fn target(&self) -> Option<IVec2> {
+        self.0.last().copied()
+    }
+
+    pub fn move_location(
+        &mut self,
+        location: &mut WorldLocation,
+        world: &Res<WorldCatacomb>,
+        occupancy: &Occupancy,
+    ) {
         if !self.has_path() {
             return;
         }
@@ -21,14 +30,23 @@ impl Path {
 
         if next_position == location.get_location() {
             self.0.remove(0);
-            self.move_location(location, world);
+            self.move_location(location, world, occupancy);
+            return;
+        }
+
+        // Someone stepped in the way, look for another path on the next tick.
+        if occupancy.is_occupied(next_position) {
+            self.0.clear();
             return;
         }
 
         let delta = next_position - location.get_location();
 
+        // Turn the shorter way, a cell behind takes two turns either way.
         if location.get_forward() == delta {
-            location.move_forward(world);
+            location.move_forward(world, occupancy);
+        } else if location.get_facing().turned(Turn::Right).to_ivec2() == delta {
+            location.turn(Turn::Right);
         } else {
             location.turn(Turn::Left);
         }
@@ -38,9 +56,10 @@ impl Path {
         &mut self,
         location: &WorldLocation,
         world: &Res<WorldCatacomb>,
+        occupancy: &Occupancy,
         target: IVec2,
     ) {
-        if !world.0.contains(&target) {
+        if !world.is_passable(target) {
             error!("Target is outside the map.");
             return;
         }
@@ -59,7 +78,9 @@ impl Path {
 
                 successors
                     .iter()
-                    .filter(|p| world.0.contains(*p))
+                    .filter(|p| {
+                        world.is_passable(**p) && (**p == target || !occupancy.is_occupied(**p))
+                    })
                     .map(|p| (*p, 1))
                     .collect::<Vec<_>>()
             },
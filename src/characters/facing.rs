@@ -0,0 +1,107 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use super::location::Turn;
+
+/// One of the four directions a character can look in on the grid, `North` being +Y.
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Facing {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Facing {
+    pub const ALL: [Facing; 4] = [Facing::North, Facing::East, Facing::South, Facing::West];
+
+    pub fn to_ivec2(self) -> IVec2 {
+        match self {
+            Facing::North => IVec2::Y,
+            Facing::East => IVec2::X,
+            Facing::South => IVec2::NEG_Y,
+            Facing::West => IVec2::NEG_X,
+        }
+    }
+
+    /// Closest direction to `delta`, preferring east and west when both axes are equal.
+    pub fn towards(delta: IVec2) -> Option<Self> {
+        if delta == IVec2::ZERO {
+            return None;
+        }
+        Some(if delta.x.abs() >= delta.y.abs() {
+            if delta.x > 0 {
+                Facing::East
+            } else {
+                Facing::West
+            }
+        } else if delta.y > 0 {
+            Facing::North
+        } else {
+            Facing::South
+        })
+    }
+
+    pub fn turned(self, dir: Turn) -> Self {
+        match (self, dir) {
+            (Facing::North, Turn::Right) | (Facing::South, Turn::Left) => Facing::East,
+            (Facing::East, Turn::Right) | (Facing::West, Turn::Left) => Facing::South,
+            (Facing::South, Turn::Right) | (Facing::North, Turn::Left) => Facing::West,
+            (Facing::West, Turn::Right) | (Facing::East, Turn::Left) => Facing::North,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        self.turned(Turn::Right).turned(Turn::Right)
+    }
+
+    /// Angle of the direction on the grid plane, counter-clockwise from east.
+    pub fn to_angle(self) -> f32 {
+        self.to_ivec2().as_vec2().to_angle()
+    }
+
+    /// World rotation of something looking in this direction.
+    pub fn rotation(self) -> Quat {
+        Quat::from_rotation_y(self.to_angle() - PI / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::utils::convert_ivec2_to_vec3_plane;
+
+    #[test]
+    fn four_turns_come_back_around() {
+        for facing in Facing::ALL {
+            let mut right = facing;
+            let mut left = facing;
+            for _ in 0..4 {
+                right = right.turned(Turn::Right);
+                left = left.turned(Turn::Left);
+            }
+            assert_eq!(right, facing);
+            assert_eq!(left, facing);
+            assert_eq!(facing.turned(Turn::Right).turned(Turn::Left), facing);
+            assert_eq!(facing.opposite().to_ivec2(), -facing.to_ivec2());
+        }
+        assert_eq!(Facing::North.turned(Turn::Right), Facing::East);
+    }
+
+    #[test]
+    fn vectors_match_directions() {
+        for facing in Facing::ALL {
+            assert_eq!(Facing::towards(facing.to_ivec2()), Some(facing));
+            assert_eq!(Facing::towards(facing.to_ivec2() * 3), Some(facing));
+
+            let forward = facing.rotation() * Vec3::NEG_Z;
+            let expected = convert_ivec2_to_vec3_plane(facing.to_ivec2());
+            assert!(forward.distance(expected) < 1e-5);
+        }
+        assert_eq!(Facing::towards(IVec2::ZERO), None);
+        assert_eq!(Facing::towards(IVec2::new(-2, 2)), Some(Facing::West));
+        assert_eq!(Facing::towards(IVec2::new(1, -3)), Some(Facing::South));
+    }
+}
@@ -4,10 +4,12 @@ use crate::{
     utils::utils::convert_ivec2_to_vec3_plane, WorldCatacomb, CAMERA_HEIGHT, F32_ROOM_SIZE,
 };
 
+use super::{facing::Facing, occupancy::Occupancy, player::control::MovementConfig};
+
 #[derive(Component, Reflect, Debug, Default, Clone)]
 pub struct WorldLocation {
     location: IVec2,
-    forward: IVec2,
+    forward: Facing,
     pub can_move: bool,
 }
 
@@ -17,7 +19,7 @@ pub enum Turn {
 }
 
 impl WorldLocation {
-    pub const fn new(start: IVec2, face: IVec2) -> Self {
+    pub const fn new(start: IVec2, face: Facing) -> Self {
         WorldLocation {
             location: start,
             forward: face,
@@ -25,16 +27,26 @@ impl WorldLocation {
         }
     }
 
-    pub fn get_forward(&self) -> IVec2 {
+    pub fn get_facing(&self) -> Facing {
         self.forward
     }
 
+    pub fn get_forward(&self) -> IVec2 {
+        self.forward.to_ivec2()
+    }
+
     pub fn get_location(&self) -> IVec2 {
         self.location
     }
 
+    pub fn set_location(&mut self, location: IVec2) {
+        self.location = location;
+    }
+
     pub fn face_towards(&mut self, position: IVec2) {
-        self.forward = position - self.location;
+        if let Some(facing) = Facing::towards(position - self.location) {
+            self.forward = facing;
+        }
     }
 
     pub fn turn(&mut self, dir: Turn) {
@@ -42,45 +54,42 @@ impl WorldLocation {
             return;
         }
 
-        let forward = self.forward;
-        match dir {
-            Turn::Right => {
-                let rot = IVec2 {
-                    x: forward.y,
-                    y: -forward.x,
-                };
-                self.forward = rot;
-            }
-            Turn::Left => {
-                let rot = IVec2 {
-                    x: -forward.y,
-                    y: forward.x,
-                };
-                self.forward = rot;
-            }
-        }
+        self.forward = self.forward.turned(dir);
     }
-    pub fn move_forward(&mut self, world: &Res<WorldCatacomb>) {
+
+    pub fn move_forward(&mut self, world: &WorldCatacomb, occupancy: &Occupancy) {
+        self.move_towards(self.forward, world, occupancy);
+    }
+
+    pub fn move_back(&mut self, world: &WorldCatacomb, occupancy: &Occupancy) {
+        self.move_towards(self.forward.opposite(), world, occupancy);
+    }
+
+    /// Sidesteps without changing where the character is facing.
+    pub fn strafe(&mut self, dir: Turn, world: &WorldCatacomb, occupancy: &Occupancy) {
+        self.move_towards(self.forward.turned(dir), world, occupancy);
+    }
+
+    fn move_towards(&mut self, dir: Facing, world: &WorldCatacomb, occupancy: &Occupancy) {
         if !self.can_move {
             return;
         }
 
-        let forward_location = self.location + self.forward;
+        let target = self.location + dir.to_ivec2();
 
-        if !world.0.contains(&forward_location) {
+        if !world.is_passable(target) || occupancy.is_occupied(target) {
             return;
         }
 
-        self.location += self.forward;
+        self.location = target;
     }
 }
 
 pub fn update_character_sprite_positions(
     mut q_characters: Query<(&WorldLocation, &mut Transform)>,
+    config: Res<MovementConfig>,
     time: Res<Time>,
 ) {
-    const LERP_SPEED: f32 = 10.0;
-
     for (loc, mut transform) in q_characters.iter_mut() {
         let location = loc.get_location();
 
@@ -89,6 +98,6 @@ pub fn update_character_sprite_positions(
 
         transform.translation = transform
             .translation
-            .lerp(final_translation, time.delta_secs() * LERP_SPEED);
+            .lerp(final_translation, time.delta_secs() * config.lerp_speed);
     }
 }
@@ -1,3 +1,5 @@
 pub mod enemy;
+pub mod facing;
 pub mod location;
+pub mod occupancy;
 pub mod player;
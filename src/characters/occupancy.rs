@@ -0,0 +1,84 @@
+use bevy::{prelude::*, utils::HashMap};
+
+/// Which character stands in which cell, so characters can't walk into each other.
+#[derive(Resource, Default)]
+pub struct Occupancy {
+    cells: HashMap<IVec2, Entity>,
+    entities: HashMap<Entity, IVec2>,
+}
+
+impl Occupancy {
+    pub fn is_occupied(&self, cell: IVec2) -> bool {
+        self.cells.contains_key(&cell)
+    }
+
+    /// Moves `entity` to `cell`, freeing the cell it stood in before.
+    pub fn set(&mut self, entity: Entity, cell: IVec2) {
+        if let Some(old) = self.entities.insert(entity, cell) {
+            if old != cell && self.cells.get(&old) == Some(&entity) {
+                self.cells.remove(&old);
+            }
+        }
+        self.cells.insert(cell, entity);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(old) = self.entities.remove(&entity) {
+            if self.cells.get(&old) == Some(&entity) {
+                self.cells.remove(&old);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.entities.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        characters::{facing::Facing, location::WorldLocation},
+        gen::ascii::AsciiMap,
+    };
+
+    #[test]
+    fn set_moves_and_remove_frees() {
+        let mut occupancy = Occupancy::default();
+        let first = Entity::from_raw(1);
+        let second = Entity::from_raw(2);
+
+        occupancy.set(first, IVec2::ZERO);
+        assert!(occupancy.is_occupied(IVec2::ZERO));
+
+        occupancy.set(first, IVec2::X);
+        assert!(!occupancy.is_occupied(IVec2::ZERO));
+        assert!(occupancy.is_occupied(IVec2::X));
+
+        occupancy.set(second, IVec2::Y);
+        occupancy.remove(first);
+        assert!(!occupancy.is_occupied(IVec2::X));
+        assert!(occupancy.is_occupied(IVec2::Y));
+
+        occupancy.remove(second);
+        assert!(!occupancy.is_occupied(IVec2::Y));
+    }
+
+    #[test]
+    fn moving_into_an_occupied_cell_is_refused() {
+        let world = AsciiMap::parse("#####\n#@..#\n#####").unwrap().0;
+        let mut occupancy = Occupancy::default();
+        let mut mover = WorldLocation::new(IVec2::ZERO, Facing::East);
+        occupancy.set(Entity::from_raw(1), IVec2::ZERO);
+        occupancy.set(Entity::from_raw(2), IVec2::X);
+
+        mover.move_forward(&world, &occupancy);
+        assert_eq!(mover.get_location(), IVec2::ZERO);
+
+        occupancy.remove(Entity::from_raw(2));
+        mover.move_forward(&world, &occupancy);
+        assert_eq!(mover.get_location(), IVec2::X);
+    }
+}
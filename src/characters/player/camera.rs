@@ -1,5 +1,3 @@
-use std::f32::consts::PI;
-
 use bevy::{
     color::Color,
     ecs::{
@@ -7,7 +5,6 @@ use bevy::{
         system::{Commands, Query, Res},
     },
     image::ImageSampler,
-    math::Quat,
     pbr::{DistanceFog, FogFalloff},
     prelude::*,
     render::{
@@ -22,34 +19,68 @@ use bevy::{
 };
 
 use bevy::render::view::RenderLayers;
+use bevy::window::{PrimaryWindow, WindowResized};
+use serde::{Deserialize, Serialize};
 
 use bevy_flycam::FlyCam;
 
 use crate::{
-    characters::location::WorldLocation, room::mesh::F32_ROOM_SIZE,
+    characters::location::WorldLocation,
+    gen::{location::WorldCatacomb, theme::ThemeConfig},
+    room::mesh::F32_ROOM_SIZE,
     utils::utils::convert_ivec2_to_vec3_plane,
 };
 
-use super::player::Player;
+use super::{control::MovementConfig, player::Player};
 
 pub(crate) const CAMERA_HEIGHT: f32 = 1.5;
 
-const RENDER_TEXTURE_WIDTH: u32 = 320;
-const RENDER_TEXTURE_HEIGHT: u32 = 200;
+/// Size of the texture the world is rendered to before it gets scaled up to the window.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct RenderResolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for RenderResolution {
+    fn default() -> Self {
+        RenderResolution {
+            width: 320,
+            height: 200,
+        }
+    }
+}
+
+impl RenderResolution {
+    /// Largest whole number of screen pixels per rendered pixel that fits in the window.
+    fn scale_for(&self, window: &Window) -> f32 {
+        let scale = (window.physical_width() / self.width.max(1))
+            .min(window.physical_height() / self.height.max(1))
+            .max(1);
+        // Sprites are sized in logical pixels.
+        scale as f32 / window.scale_factor()
+    }
+}
+
+#[derive(Component)]
+pub struct RenderOutput;
 
 pub fn setup_camera(
     mut commands: Commands,
     q_fly_cam: Query<&FlyCam>,
     mut images: ResMut<Assets<Image>>,
     q_player: Query<(Entity, &Player)>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    resolution: Res<RenderResolution>,
 ) {
     if !q_fly_cam.is_empty() {
         return;
     }
 
     let size = Extent3d {
-        width: RENDER_TEXTURE_WIDTH,
-        height: RENDER_TEXTURE_HEIGHT,
+        width: resolution.width,
+        height: resolution.height,
         ..default()
     };
 
@@ -90,20 +121,49 @@ pub fn setup_camera(
         }),
     ));
 
+    let scale = q_window
+        .get_single()
+        .map_or(1.0, |window| resolution.scale_for(window));
+
     commands.spawn((
+        RenderOutput,
         Sprite {
             image: render_texture_handle,
-            custom_size: Some(Vec2::new(
-                RENDER_TEXTURE_WIDTH as f32,
-                RENDER_TEXTURE_HEIGHT as f32,
-            )),
+            custom_size: Some(Vec2::new(resolution.width as f32, resolution.height as f32)),
+            ..default()
+        },
+        Transform::from_scale(Vec3::splat(scale)),
+        RenderLayers::layer(1),
+    ));
+
+    // Whatever the scaled image doesn't cover is letterboxed in black.
+    commands.spawn((
+        Camera2d,
+        Camera {
+            clear_color: Color::BLACK.into(),
             ..default()
         },
-        Transform::from_scale(Vec3::splat(4.0)),
         RenderLayers::layer(1),
     ));
+}
 
-    commands.spawn((Camera2d::default(), RenderLayers::layer(1)));
+pub fn scale_render_output(
+    mut resized: EventReader<WindowResized>,
+    resolution: Res<RenderResolution>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_output: Query<&mut Transform, With<RenderOutput>>,
+) {
+    if resized.read().last().is_none() {
+        return;
+    }
+    let Ok(window) = q_window.get_single() else {
+        return;
+    };
+
+    let scale = resolution.scale_for(window);
+    for mut transform in q_output.iter_mut() {
+        transform.scale = Vec3::splat(scale);
+    }
 }
 
 pub fn spawn_fog(mut commands: Commands, q_camera: Query<(Entity, &Camera), With<WorldLocation>>) {
@@ -118,17 +178,33 @@ pub fn spawn_fog(mut commands: Commands, q_camera: Query<(Entity, &Camera), With
     });
 }
 
+/// Fades to the fog colour of the theme the player is standing in.
+pub fn update_fog(
+    location: Res<WorldCatacomb>,
+    themes: Res<ThemeConfig>,
+    mut q_camera: Query<(&WorldLocation, &mut DistanceFog), Changed<WorldLocation>>,
+) {
+    let Ok((camera_location, mut fog)) = q_camera.get_single_mut() else {
+        return;
+    };
+    let Some(theme) = location
+        .get(camera_location.get_location())
+        .and_then(|tile| themes.theme(tile.meta.theme))
+    else {
+        return;
+    };
+
+    fog.color = Color::srgb_from_array(theme.fog_color);
+}
+
 pub fn sync_camera(
     q_player: Query<&WorldLocation, With<Player>>,
     mut q_camera: Query<(&mut Transform, &Camera), With<WorldLocation>>,
+    config: Res<MovementConfig>,
     time: Res<Time>,
 ) {
-    const LERP_SPEED: f32 = 10.0;
-
     let player_location = q_player.single();
-    let forward = player_location.get_forward().as_vec2();
     let location = player_location.get_location();
-    let angle = forward.to_angle();
     let (mut transform, _) = q_camera.single_mut();
 
     let mut final_translation = convert_ivec2_to_vec3_plane(location) * F32_ROOM_SIZE;
@@ -136,9 +212,9 @@ pub fn sync_camera(
 
     transform.translation = transform
         .translation
-        .lerp(final_translation, time.delta_secs() * LERP_SPEED);
+        .lerp(final_translation, time.delta_secs() * config.lerp_speed);
     transform.rotation = transform.rotation.lerp(
-        Quat::from_rotation_y(angle - PI / 2.0),
-        time.delta_secs() * LERP_SPEED,
+        player_location.get_facing().rotation(),
+        time.delta_secs() * config.lerp_speed,
     )
 }
@@ -1,31 +1,199 @@
+use std::collections::VecDeque;
+
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    characters::location::{Turn, WorldLocation},
+    characters::{
+        location::{Turn, WorldLocation},
+        occupancy::Occupancy,
+    },
+    combat::combat::CombatState,
     gen::location::WorldCatacomb,
-    tick::tick::TickEvent,
+    input::input::{ActionState, InputAction},
+    replay::replay::RecordedAction,
+    room::mesh::F32_ROOM_SIZE,
+    tick::tick::{Ticks, TurnClock},
+    utils::utils::convert_ivec2_to_vec3_plane,
 };
 
-use super::player::Player;
+use super::{camera::CAMERA_HEIGHT, player::Player, rest::Rest, travel::Travel};
+
+/// How close the camera has to get to its cell and facing before the next move starts.
+const SETTLE_DISTANCE: f32 = 0.1;
+const SETTLE_ANGLE: f32 = 0.1;
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct MovementConfig {
+    /// How many actions can be queued up while a move is still playing out.
+    pub buffer_size: usize,
+    /// How quickly the camera and characters glide to their new cell and facing.
+    pub lerp_speed: f32,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        MovementConfig {
+            buffer_size: 2,
+            lerp_speed: 10.0,
+        }
+    }
+}
+
+/// Actions pressed while the player was still moving, run one at a time once each move finishes.
+#[derive(Resource, Default, Debug)]
+pub struct MovementQueue(VecDeque<InputAction>);
+
+impl MovementQueue {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Drops the action if the queue is already full.
+    fn push(&mut self, action: InputAction, capacity: usize) -> bool {
+        if self.0.len() >= capacity {
+            return false;
+        }
+        self.0.push_back(action);
+        true
+    }
+
+    /// While the player can't move only attacks and waits go through, queued moves are dropped
+    /// so they neither fill the queue nor run once the fight is over.
+    fn next(&mut self, can_move: bool) -> Option<InputAction> {
+        if !can_move {
+            self.0.retain(|action| is_allowed(*action, can_move));
+        }
+        self.0.pop_front()
+    }
+}
+
+fn is_allowed(action: InputAction, can_move: bool) -> bool {
+    can_move || matches!(action, InputAction::Attack | InputAction::Wait)
+}
+
+pub(crate) fn is_settled(location: &WorldLocation, transform: &Transform) -> bool {
+    let mut target = convert_ivec2_to_vec3_plane(location.get_location()) * F32_ROOM_SIZE;
+    target.y = CAMERA_HEIGHT;
+
+    transform.translation.distance(target) < SETTLE_DISTANCE
+        && transform
+            .rotation
+            .angle_between(location.get_facing().rotation())
+            < SETTLE_ANGLE
+}
+
+/// Queues the moves pressed this frame. Presses are recorded with the turn the frame started on,
+/// before anything got to spend a tick, so a replay presses them at the same point.
+pub fn queue_actions(
+    actions: Res<ActionState>,
+    config: Res<MovementConfig>,
+    clock: Res<TurnClock>,
+    mut queue: ResMut<MovementQueue>,
+    mut travel: ResMut<Travel>,
+    mut rest: ResMut<Rest>,
+    mut ev_action: EventWriter<RecordedAction>,
+    q_player: Query<&WorldLocation, With<Player>>,
+) {
+    let player_loc = q_player.single();
+
+    for action in InputAction::ALL {
+        if !actions.just_pressed(action) {
+            continue;
+        }
+        // Travel and rest are recorded by the systems that start them. Interact is read straight
+        // away by the stairs.
+        if action.is_queued() {
+            if !is_allowed(action, player_loc.can_move) {
+                continue;
+            }
+            // Taking over by hand stops any travel or rest.
+            travel.stop();
+            rest.stop();
+            if !queue.push(action, config.buffer_size) {
+                continue;
+            }
+        } else if action != InputAction::Interact {
+            continue;
+        }
+        ev_action.send(RecordedAction {
+            turn: clock.turn(),
+            action,
+            cell: None,
+        });
+    }
+}
 
 pub fn move_player(
     world: Res<WorldCatacomb>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    mut ev_tick: EventWriter<TickEvent>,
-    mut q_player: Query<&mut WorldLocation, With<Player>>,
+    mut queue: ResMut<MovementQueue>,
+    mut combat_state: ResMut<CombatState>,
+    mut ticks: Ticks,
+    mut occupancy: ResMut<Occupancy>,
+    mut q_player: Query<(Entity, &mut WorldLocation, Option<&Transform>), With<Player>>,
 ) {
-    let mut player_loc = q_player.single_mut();
-    if keyboard.just_pressed(KeyCode::KeyA) {
-        ev_tick.send(TickEvent);
-        player_loc.turn(Turn::Left)
-    }
-    if keyboard.just_pressed(KeyCode::KeyD) {
-        ev_tick.send(TickEvent);
-        player_loc.turn(Turn::Right)
-    }
-    if mouse.just_pressed(MouseButton::Left) {
-        ev_tick.send(TickEvent);
-        player_loc.move_forward(&world);
+    let (player, mut player_loc, transform) = q_player.single_mut();
+
+    if transform.is_some_and(|transform| !is_settled(&player_loc, transform)) {
+        return;
+    }
+    // Fights wait for the last attack to play out before the next turn.
+    if combat_state.opponent.is_some() && !combat_state.cooldown.finished() {
+        return;
+    }
+    let Some(action) = queue.next(player_loc.can_move) else {
+        return;
+    };
+
+    ticks.spend();
+    match action {
+        InputAction::TurnLeft => player_loc.turn(Turn::Left),
+        InputAction::TurnRight => player_loc.turn(Turn::Right),
+        InputAction::Forward => player_loc.move_forward(&world, &occupancy),
+        InputAction::Back => player_loc.move_back(&world, &occupancy),
+        InputAction::StrafeLeft => player_loc.strafe(Turn::Left, &world, &occupancy),
+        InputAction::StrafeRight => player_loc.strafe(Turn::Right, &world, &occupancy),
+        // The combat systems strike on the player's next turn. Waiting just lets the turn pass.
+        InputAction::Attack if combat_state.opponent.is_some() => {
+            combat_state.player_attacking = true;
+        }
+        _ => {}
+    }
+
+    // Claim the cell right away so enemies moving this frame can't take it.
+    occupancy.set(player, player_loc.get_location());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_runs_actions_in_order_up_to_its_size() {
+        let mut queue = MovementQueue::default();
+
+        assert!(queue.push(InputAction::Forward, 2));
+        assert!(queue.push(InputAction::TurnLeft, 2));
+        assert!(!queue.push(InputAction::Back, 2));
+
+        assert_eq!(queue.next(true), Some(InputAction::Forward));
+        assert_eq!(queue.next(true), Some(InputAction::TurnLeft));
+        assert_eq!(queue.next(true), None);
+    }
+
+    #[test]
+    fn queue_drops_moves_while_the_player_cant_move() {
+        let mut queue = MovementQueue::default();
+        queue.push(InputAction::Forward, 3);
+        queue.push(InputAction::Attack, 3);
+        queue.push(InputAction::StrafeLeft, 3);
+
+        assert_eq!(queue.next(false), Some(InputAction::Attack));
+        assert!(queue.is_empty());
     }
 }
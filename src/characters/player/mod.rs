@@ -1,3 +1,5 @@
 pub mod camera;
 pub mod control;
 pub mod player;
+pub mod rest;
+pub mod travel;
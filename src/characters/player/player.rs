@@ -2,20 +2,35 @@ use bevy::math::IVec2;
 use bevy::prelude::*;
 use bevy_flycam::FlyCam;
 
-use crate::characters::location::WorldLocation;
-use crate::combat::combat::{Combat, Health};
+use crate::characters::{facing::Facing, location::WorldLocation};
+use crate::combat::{
+    combat::{Combat, Health},
+    regeneration::Regeneration,
+    weapon::{Weapon, PLAYER_WEAPON},
+};
+use crate::tick::tick::Speed;
 
 #[derive(Component)]
-#[require(WorldLocation(new_player_location), Health, Combat)]
+#[require(
+    WorldLocation(new_player_location),
+    Health,
+    Combat,
+    Speed,
+    Regeneration
+)]
 pub struct Player;
 
 fn new_player_location() -> WorldLocation {
-    WorldLocation::new(default(), IVec2::Y)
+    WorldLocation::new(IVec2::ZERO, Facing::North)
 }
 
-pub fn setup_player(mut commands: Commands, q_fly_cam: Query<&FlyCam>) {
+pub fn setup_player(
+    mut commands: Commands,
+    q_fly_cam: Query<&FlyCam>,
+    asset_server: Res<AssetServer>,
+) {
     if !q_fly_cam.is_empty() {
         return;
     }
-    commands.spawn(Player);
+    commands.spawn((Player, Weapon::new(asset_server.load(PLAYER_WEAPON))));
 }
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use crate::{
+    characters::{enemy::enemy::Enemy, location::WorldLocation},
+    combat::combat::{CombatState, Health, MaxHealth},
+    gen::location::WorldCatacomb,
+    input::input::{ActionState, InputAction},
+    replay::replay::RecordedAction,
+    tick::tick::Ticks,
+};
+
+use super::{
+    control::{is_settled, MovementQueue},
+    player::Player,
+    travel::{enemy_in_view, Travel},
+};
+
+/// Passes a tick every frame until the player is healed or interrupted.
+#[derive(Resource, Default)]
+pub struct Rest {
+    active: bool,
+}
+
+impl Rest {
+    pub fn stop(&mut self) {
+        self.active = false;
+    }
+}
+
+pub fn rest_player(
+    actions: Res<ActionState>,
+    world: Res<WorldCatacomb>,
+    combat_state: Res<CombatState>,
+    queue: Res<MovementQueue>,
+    mut rest: ResMut<Rest>,
+    mut travel: ResMut<Travel>,
+    mut ticks: Ticks,
+    mut ev_action: EventWriter<RecordedAction>,
+    q_player: Query<(&WorldLocation, Option<&Transform>, &Health, &MaxHealth), With<Player>>,
+    q_enemies: Query<&WorldLocation, With<Enemy>>,
+) {
+    let (player_loc, transform, health, max_health) = q_player.single();
+
+    if actions.just_pressed(InputAction::Rest) {
+        rest.active = true;
+        travel.stop();
+        // Rest runs before travel and moves, nothing spent a tick yet this frame.
+        ev_action.send(RecordedAction {
+            turn: ticks.turn(),
+            action: InputAction::Rest,
+            cell: None,
+        });
+    }
+    if !rest.active {
+        return;
+    }
+
+    if health.0 >= max_health.0 {
+        info!("Fully rested.");
+        rest.stop();
+        return;
+    }
+    if combat_state.opponent.is_some()
+        || enemy_in_view(&world, player_loc.get_location(), q_enemies.iter())
+    {
+        info!("An enemy is near, stopping the rest.");
+        rest.stop();
+        return;
+    }
+    // Enemies get to finish their moves before the next tick, so they can be seen coming.
+    if !queue.is_empty() || transform.is_some_and(|transform| !is_settled(player_loc, transform)) {
+        return;
+    }
+
+    ticks.spend();
+}
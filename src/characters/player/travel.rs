@@ -0,0 +1,218 @@
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, Window},
+};
+
+use crate::{
+    characters::{
+        enemy::{enemy::Enemy, path::Path},
+        location::WorldLocation,
+        occupancy::Occupancy,
+    },
+    combat::combat::CombatState,
+    gen::location::WorldCatacomb,
+    input::input::{ActionState, InputAction},
+    replay::replay::RecordedAction,
+    room::mesh::F32_ROOM_SIZE,
+    tick::tick::{Ticks, TurnClock},
+    utils::utils::convert_vec3_plane_to_ivec2,
+};
+
+use super::{
+    camera::{RenderOutput, RenderResolution},
+    control::{is_settled, MovementQueue},
+    player::Player,
+    rest::Rest,
+};
+
+/// How many cells away an enemy in plain sight interrupts travel.
+const VIEW_DISTANCE: i32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    /// The closest cell the player hasn't seen yet, picked again whenever it gets explored.
+    Unexplored,
+    Cell(IVec2),
+}
+
+/// Walks the player along a path, one tick per step, until they arrive or something shows up.
+#[derive(Resource, Default)]
+pub struct Travel {
+    destination: Option<Destination>,
+    path: Path,
+}
+
+impl Travel {
+    pub fn start(&mut self, destination: Destination) {
+        self.destination = Some(destination);
+        self.path = Path::default();
+    }
+
+    pub fn stop(&mut self) {
+        self.destination = None;
+        self.path = Path::default();
+    }
+
+    /// Whether the player is only walking over `cell` on the way somewhere else.
+    pub fn is_passing(&self, cell: IVec2) -> bool {
+        self.destination.is_some() && self.path.target() != Some(cell)
+    }
+}
+
+/// Whether any of `enemies` stands close enough to `cell`, with nothing in between, to be seen.
+pub(crate) fn enemy_in_view<'a>(
+    world: &WorldCatacomb,
+    cell: IVec2,
+    mut enemies: impl Iterator<Item = &'a WorldLocation>,
+) -> bool {
+    enemies.any(|enemy| {
+        let delta = enemy.get_location() - cell;
+        delta.abs().max_element() <= VIEW_DISTANCE && world.can_see(cell, enemy.get_location())
+    })
+}
+
+fn nearest_unexplored(world: &WorldCatacomb, from: IVec2) -> Option<IVec2> {
+    world
+        .distances_from(from)
+        .into_iter()
+        .filter(|(cell, _)| world.get(*cell).is_some_and(|tile| !tile.meta.explored))
+        .min_by_key(|(cell, distance)| (*distance, cell.x, cell.y))
+        .map(|(cell, _)| cell)
+}
+
+/// Casts a ray from the cursor through the scaled render output and returns the floor cell it hits.
+fn cell_under_cursor(
+    window: &Window,
+    resolution: &RenderResolution,
+    output: &Transform,
+    camera: (&Camera, &GlobalTransform),
+) -> Option<IVec2> {
+    let cursor = window.cursor_position()?;
+    let window_center = Vec2::new(window.width(), window.height()) / 2.0;
+    let render_size = Vec2::new(resolution.width as f32, resolution.height as f32);
+
+    let viewport = (cursor - window_center) / output.scale.truncate() + render_size / 2.0;
+    if viewport.cmplt(Vec2::ZERO).any() || viewport.cmpge(render_size).any() {
+        return None;
+    }
+
+    let (camera, camera_transform) = camera;
+    let ray = camera.viewport_to_world(camera_transform, viewport).ok()?;
+    let distance = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))?;
+    Some(convert_vec3_plane_to_ivec2(
+        ray.get_point(distance) / F32_ROOM_SIZE,
+    ))
+}
+
+pub fn start_travel(
+    actions: Res<ActionState>,
+    world: Res<WorldCatacomb>,
+    resolution: Res<RenderResolution>,
+    mut travel: ResMut<Travel>,
+    mut rest: ResMut<Rest>,
+    mut ev_action: EventWriter<RecordedAction>,
+    clock: Res<TurnClock>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_output: Query<&Transform, With<RenderOutput>>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<Player>>,
+) {
+    if actions.just_pressed(InputAction::AutoExplore) {
+        travel.start(Destination::Unexplored);
+        rest.stop();
+        ev_action.send(RecordedAction {
+            turn: clock.turn(),
+            action: InputAction::AutoExplore,
+            cell: None,
+        });
+    }
+    if actions.just_pressed(InputAction::TravelTo) {
+        let (Ok(window), Ok(output), Ok(camera)) = (
+            q_window.get_single(),
+            q_output.get_single(),
+            q_camera.get_single(),
+        ) else {
+            return;
+        };
+        let Some(cell) = cell_under_cursor(window, &resolution, output, camera) else {
+            return;
+        };
+        if world.get(cell).is_some_and(|tile| tile.meta.explored) && world.is_passable(cell) {
+            travel.start(Destination::Cell(cell));
+            rest.stop();
+            ev_action.send(RecordedAction {
+                turn: clock.turn(),
+                action: InputAction::TravelTo,
+                cell: Some(cell),
+            });
+        }
+    }
+}
+
+pub fn travel_player(
+    world: Res<WorldCatacomb>,
+    combat_state: Res<CombatState>,
+    queue: Res<MovementQueue>,
+    mut travel: ResMut<Travel>,
+    mut occupancy: ResMut<Occupancy>,
+    mut ticks: Ticks,
+    mut q_player: Query<(Entity, &mut WorldLocation, Option<&Transform>), With<Player>>,
+    q_enemies: Query<&WorldLocation, (With<Enemy>, Without<Player>)>,
+) {
+    let Some(destination) = travel.destination else {
+        return;
+    };
+    let (player, mut player_loc, transform) = q_player.single_mut();
+    let cell = player_loc.get_location();
+
+    if combat_state.opponent.is_some() {
+        info!("Combat started, stopping.");
+        travel.stop();
+        return;
+    }
+    if enemy_in_view(&world, cell, q_enemies.iter()) {
+        info!("An enemy came into view, stopping.");
+        travel.stop();
+        return;
+    }
+
+    if !queue.is_empty() || transform.is_some_and(|transform| !is_settled(&player_loc, transform)) {
+        return;
+    }
+
+    let explored = |cell| world.get(cell).is_none_or(|tile| tile.meta.explored);
+    let target = match destination {
+        Destination::Unexplored => travel.path.target().filter(|target| !explored(*target)),
+        Destination::Cell(target) => travel.path.target().or(Some(target)),
+    };
+    let target = match target {
+        Some(target) => target,
+        None => match nearest_unexplored(&world, cell) {
+            Some(target) => target,
+            None => {
+                info!("Nothing left to explore.");
+                travel.stop();
+                return;
+            }
+        },
+    };
+    if target == cell {
+        travel.stop();
+        return;
+    }
+
+    if travel.path.target() != Some(target) {
+        travel
+            .path
+            .find_path(&player_loc, &world, &occupancy, target);
+        if !travel.path.has_path() {
+            travel.stop();
+            return;
+        }
+    }
+
+    travel
+        .path
+        .move_location(&mut player_loc, &world, &occupancy);
+    ticks.spend();
+    occupancy.set(player, player_loc.get_location());
+}
@@ -1,12 +1,18 @@
+use std::time::Duration;
+
 use bevy::{audio::Source, prelude::*};
 use bevy_sprite3d::{Sprite3dBuilder, Sprite3dParams};
 use rand::seq::IteratorRandom;
-use rand::{seq::SliceRandom, thread_rng};
 
 use crate::audio::random::RandomAudio;
+use crate::rng::rng::GameRng;
 use crate::{
-    characters::{enemy::enemy::Enemy, location::WorldLocation, player::player::Player},
-    tick::tick::TickEvent,
+    characters::{
+        enemy::enemy::Enemy, location::WorldLocation, occupancy::Occupancy, player::player::Player,
+    },
+    dungeon::dungeon::FloorEntity,
+    gen::location::WorldCatacomb,
+    tick::tick::TurnEvent,
     visuals::{
         animation::{AnimationTimer, Animations},
         billboard::Billboard,
@@ -15,28 +21,32 @@ use crate::{
 
 use super::death::DeathSound;
 use super::pain::{self, PainSound};
+use super::weapon::{Shot, Weapon, WeaponDef};
 
 #[derive(Resource)]
 pub struct CombatState {
+    /// Only paces attacks so their animations and sounds don't overlap, turns decide who hits.
     pub cooldown: Timer,
     pub opponent: Option<Entity>,
-    pub is_player_turn: bool,
+    /// Set by the attack action, the player strikes on their next turn.
+    pub player_attacking: bool,
 }
 
 #[derive(Component, Default, DerefMut, Deref)]
 pub struct CombatReplica(pub RandomAudio);
 
+/// The entity attacks its opponent.
 #[derive(Event)]
-pub struct CombatEvent(Entity); // TODO: Remove this or find the proper usage.
+pub struct CombatEvent(pub Entity);
 
 #[derive(Component, Default, Debug)]
-#[require(Health, CombatReplica)]
+#[require(Health, MaxHealth, CombatReplica)]
 pub struct Combat {
     pub is_in_combat: bool,
 }
 
 #[derive(Component)]
-pub struct Health(i32);
+pub struct Health(pub i32);
 
 impl Default for Health {
     fn default() -> Self {
@@ -44,199 +54,312 @@ impl Default for Health {
     }
 }
 
+/// Highest health regeneration and resting can bring an actor back up to.
+#[derive(Component)]
+pub struct MaxHealth(pub i32);
+
+impl Default for MaxHealth {
+    fn default() -> Self {
+        MaxHealth(Health::default().0)
+    }
+}
+
 #[derive(Event)]
 pub struct DamagedEvent(pub Entity);
 
+fn play_sound(commands: &mut Commands, sound: Handle<AudioSource>) {
+    commands.spawn((
+        AudioPlayer(sound),
+        PlaybackSettings {
+            mode: bevy::audio::PlaybackMode::Despawn,
+            ..default()
+        },
+    ));
+}
+
+/// Opponents have to stand in a straight line with nothing in between, so the player can face them.
+fn in_range(world: &WorldCatacomb, from: IVec2, to: IVec2, range: i32) -> bool {
+    let delta = to - from;
+    (delta.x == 0 || delta.y == 0) && delta.abs().max_element() <= range && world.can_see(from, to)
+}
+
+/// Holds back the next attack or wait until the weapon that just fired has played out.
+fn start_cooldown(combat_state: &mut CombatState, weapon: &WeaponDef) {
+    combat_state
+        .cooldown
+        .set_duration(Duration::from_secs_f32(weapon.cooldown.max(0.0)));
+    combat_state.cooldown.reset();
+}
+
 pub fn update_combat(
-    mut q_player: Query<(&mut WorldLocation, &Player, &mut Combat), Without<Enemy>>,
-    mut q_enemies: Query<(&mut WorldLocation, &Enemy, Entity, &CombatReplica)>,
-    mut ev_tick: EventReader<TickEvent>,
+    mut q_player: Query<(&mut WorldLocation, &Player, &mut Combat, &Weapon), Without<Enemy>>,
+    mut q_enemies: Query<(&mut WorldLocation, &Enemy, Entity, &CombatReplica, &Weapon)>,
+    mut ev_turn: EventReader<TurnEvent>,
     mut combat_state: ResMut<CombatState>,
     mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    world: Res<WorldCatacomb>,
+    weapons: Res<Assets<WeaponDef>>,
+    time: Res<Time>,
 ) {
-    let (mut player_location, _, mut player_combat) = q_player.single_mut();
-    player_combat.is_in_combat = combat_state.opponent.is_some();
-    player_location.can_move = combat_state.opponent.is_none();
-    if player_combat.is_in_combat {
-        return;
-    }
-    for _ in ev_tick.read() {
-        for (mut enemy_location, _, enemy_entity, enemy_replicas) in q_enemies.iter_mut() {
-            let distance = enemy_location
-                .get_location()
-                .distance_squared(player_location.get_location());
-            if distance == 1 && enemy_location.can_move {
-                player_location.face_towards(enemy_location.get_location());
-                combat_state.opponent = Some(enemy_entity);
-                enemy_location.can_move = false;
-                if let Some(replica) = enemy_replicas.pick() {
-                    commands.spawn((
-                        AudioPlayer(replica.clone()),
-                        PlaybackSettings {
-                            mode: bevy::audio::PlaybackMode::Despawn,
-                            ..default()
-                        },
-                    ));
-                }
+    combat_state.cooldown.tick(time.delta());
 
-                break;
+    let (mut player_location, _, mut player_combat, player_weapon) = q_player.single_mut();
+    let player_weapon_def = weapons.get(&player_weapon.def);
+    match combat_state.opponent {
+        None => {
+            combat_state.player_attacking = false;
+            // An enemy starts a fight on its turn once both sides can reach each other.
+            for turn in ev_turn.read() {
+                let Ok((mut enemy_location, _, enemy_entity, enemy_replicas, enemy_weapon)) =
+                    q_enemies.get_mut(turn.actor)
+                else {
+                    continue;
+                };
+                let enemy_range = weapons
+                    .get(&enemy_weapon.def)
+                    .map_or(1, |weapon| weapon.range);
+                let range =
+                    player_weapon_def.map_or(enemy_range, |weapon| weapon.range.min(enemy_range));
+                if enemy_location.can_move
+                    && in_range(
+                        &world,
+                        enemy_location.get_location(),
+                        player_location.get_location(),
+                        range,
+                    )
+                {
+                    info!("Turn {}: an enemy attacks.", turn.turn);
+                    player_location.face_towards(enemy_location.get_location());
+                    combat_state.opponent = Some(enemy_entity);
+                    enemy_location.can_move = false;
+                    if let Some(replica) = enemy_replicas.pick(rng.as_mut()) {
+                        play_sound(&mut commands, replica.clone());
+                    }
+
+                    break;
+                }
+            }
+        }
+        // A player who walked out of the enemy's reach got away.
+        Some(opponent) => {
+            if let Ok((mut enemy_location, _, _, _, enemy_weapon)) = q_enemies.get_mut(opponent) {
+                let enemy_range = weapons
+                    .get(&enemy_weapon.def)
+                    .map_or(1, |weapon| weapon.range);
+                if !in_range(
+                    &world,
+                    enemy_location.get_location(),
+                    player_location.get_location(),
+                    enemy_range,
+                ) {
+                    info!("Escaped the fight.");
+                    combat_state.opponent = None;
+                    combat_state.player_attacking = false;
+                    enemy_location.can_move = true;
+                }
             }
         }
     }
+
+    // The player is only held in place while they can still hit back.
+    let can_attack = match (
+        combat_state
+            .opponent
+            .and_then(|opponent| q_enemies.get(opponent).ok()),
+        player_weapon_def,
+    ) {
+        (Some((enemy_location, ..)), Some(weapon)) => {
+            player_weapon.has_ammo(weapon)
+                && in_range(
+                    &world,
+                    player_location.get_location(),
+                    enemy_location.get_location(),
+                    weapon.range,
+                )
+        }
+        _ => true,
+    };
+    // Synced after a fight starts, so the player stops on the same tick.
+    player_combat.is_in_combat = combat_state.opponent.is_some();
+    player_location.can_move = combat_state.opponent.is_none() || !can_attack;
 }
 
 pub fn damage_enemy(
-    asset_server: Res<AssetServer>,
+    weapons: Res<Assets<WeaponDef>>,
+    world: Res<WorldCatacomb>,
     mut commands: Commands,
-    mut q_player: Query<(&Player, &mut Combat, &mut WorldLocation), Without<Enemy>>,
-    mut q_enemies: Query<(&mut Health, &Enemy, &mut AnimationTimer, &PainSound, Entity)>,
+    mut q_player: Query<(Entity, &mut Weapon, &mut Combat, &mut WorldLocation), With<Player>>,
+    mut q_enemies: Query<
+        (
+            &mut Health,
+            &WorldLocation,
+            &mut AnimationTimer,
+            &PainSound,
+            Entity,
+        ),
+        (With<Enemy>, Without<Player>),
+    >,
     mut ev_combat: EventReader<CombatEvent>,
     mut ev_damaged: EventWriter<DamagedEvent>,
-    combat_state: ResMut<CombatState>,
+    mut combat_state: ResMut<CombatState>,
+    mut rng: ResMut<GameRng>,
 ) {
-    if !combat_state.is_player_turn {
-        return;
-    }
+    let (player_entity, mut player_weapon, mut player_combat, mut player_location) =
+        q_player.single_mut();
 
-    for _ in ev_combat.read() {
-        let (_, mut player_combat, mut player_location) = q_player.single_mut();
-        if !player_combat.is_in_combat {
-            return;
+    for attack in ev_combat.read() {
+        if attack.0 != player_entity || !player_combat.is_in_combat {
+            continue;
         }
-
-        let Ok((mut enemy_health, _, mut enemy_animation, enemy_pain_sound, enemy_entity)) =
-            q_enemies.get_mut(combat_state.opponent.unwrap())
+        let Some(opponent) = combat_state.opponent else {
+            continue;
+        };
+        let Ok((
+            mut enemy_health,
+            enemy_location,
+            mut enemy_animation,
+            enemy_pain_sound,
+            enemy_entity,
+        )) = q_enemies.get_mut(opponent)
         else {
-            return;
+            continue;
+        };
+        let Some(weapon) = weapons.get(&player_weapon.def) else {
+            continue;
+        };
+
+        if !in_range(
+            &world,
+            player_location.get_location(),
+            enemy_location.get_location(),
+            weapon.range,
+        ) {
+            info!("Out of range of the {}.", weapon.name);
+            continue;
+        }
+        start_cooldown(&mut combat_state, weapon);
+        let damage = match player_weapon.fire(weapon, rng.as_mut()) {
+            Shot::Hit(damage) => damage,
+            Shot::Miss => {
+                play_sound(&mut commands, weapon.fire_sound.clone());
+                continue;
+            }
+            Shot::OutOfAmmo => {
+                info!("The {} is out of ammo.", weapon.name);
+                continue;
+            }
         };
 
-        enemy_health.0 -= 30;
+        enemy_health.0 -= damage;
         enemy_animation.play("pain".to_string(), Some("walk".to_string()));
 
-        commands.spawn((
-            AudioPlayer(asset_server.load::<AudioSource>("sounds/weapon/shot_fire2.wav")),
-            PlaybackSettings {
-                mode: bevy::audio::PlaybackMode::Despawn,
-                ..default()
-            },
-        ));
+        play_sound(&mut commands, weapon.fire_sound.clone());
+        if let Some(hit_sound) = &weapon.hit_sound {
+            play_sound(&mut commands, hit_sound.clone());
+        }
 
         ev_damaged.send(DamagedEvent(enemy_entity));
         if enemy_health.0 <= 0 {
             player_combat.is_in_combat = false;
             player_location.can_move = true;
-        } else if let Some(pain_sound) = enemy_pain_sound.pick() {
-            commands.spawn((
-                AudioPlayer(pain_sound.clone()),
-                PlaybackSettings {
-                    mode: bevy::audio::PlaybackMode::Despawn,
-                    ..default()
-                },
-            ));
+        } else if let Some(pain_sound) = enemy_pain_sound.pick(rng.as_mut()) {
+            play_sound(&mut commands, pain_sound.clone());
         }
-        break;
     }
 }
 
+/// Strikes the opponent on the player's turn, if the player chose to attack.
 pub fn check_player_combat(
-    mut q_player: Query<(&mut Combat, Entity), With<Player>>,
-    mut ev_tick: EventReader<TickEvent>,
+    q_player: Query<Entity, With<Player>>,
+    mut ev_turn: EventReader<TurnEvent>,
     mut ev_combat: EventWriter<CombatEvent>,
     mut combat_state: ResMut<CombatState>,
-    time: Res<Time>,
 ) {
-    combat_state.cooldown.tick(time.delta());
+    let player_entity = q_player.single();
 
-    if !combat_state.cooldown.finished() {
-        return;
-    }
-
-    if !combat_state.is_player_turn {
-        return;
-    }
-
-    for _ in ev_tick.read() {
-        let (player_combat, player_entity) = q_player.single_mut();
-        if !player_combat.is_in_combat {
-            return;
+    for turn in ev_turn.read() {
+        if turn.actor != player_entity || !combat_state.player_attacking {
+            continue;
+        }
+        combat_state.player_attacking = false;
+        if combat_state.opponent.is_some() {
+            ev_combat.send(CombatEvent(player_entity));
         }
-
-        ev_combat.send(CombatEvent(player_entity));
-        combat_state.is_player_turn = false;
-        combat_state.cooldown.reset();
     }
 }
 
+/// The opponent strikes back on every one of its turns.
 pub fn check_enemy_combat(
-    mut q_enemies: Query<Entity, With<Enemy>>,
-    mut ev_tick: EventReader<TickEvent>,
+    mut ev_turn: EventReader<TurnEvent>,
     mut ev_combat: EventWriter<CombatEvent>,
-    mut combat_state: ResMut<CombatState>,
-    time: Res<Time>,
+    combat_state: Res<CombatState>,
 ) {
-    combat_state.cooldown.tick(time.delta());
-
-    if !combat_state.cooldown.finished() {
-        return;
-    }
-
-    if combat_state.opponent.is_none() {
-        return;
-    }
-
-    if combat_state.is_player_turn {
-        return;
-    }
-
-    for _ in ev_tick.read() {
-        let Ok(enemy_entity) = q_enemies.get_mut(combat_state.opponent.unwrap()) else {
-            return;
-        };
-        combat_state.cooldown.reset();
-        combat_state.is_player_turn = true;
-
-        ev_combat.send(CombatEvent(enemy_entity));
+    for turn in ev_turn.read() {
+        if combat_state.opponent == Some(turn.actor) {
+            ev_combat.send(CombatEvent(turn.actor));
+        }
     }
 }
 
 pub fn damage_player(
-    mut q_player: Query<(&mut Health, &Player, &Combat, Entity), Without<Enemy>>,
-    mut q_enemies: Query<(&Enemy, &mut AnimationTimer)>,
+    mut q_player: Query<
+        (&mut Health, &WorldLocation, &Combat, Entity),
+        (With<Player>, Without<Enemy>),
+    >,
+    mut q_enemies: Query<(&Health, &WorldLocation, &mut AnimationTimer, &mut Weapon), With<Enemy>>,
     mut ev_combat: EventReader<CombatEvent>,
     mut ev_damaged: EventWriter<DamagedEvent>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    combat_state: ResMut<CombatState>,
+    weapons: Res<Assets<WeaponDef>>,
+    world: Res<WorldCatacomb>,
+    mut combat_state: ResMut<CombatState>,
+    mut rng: ResMut<GameRng>,
 ) {
-    use bevy::audio::AudioSource;
-
-    if combat_state.is_player_turn {
-        return;
-    }
+    let (mut player_health, player_location, player_combat, player_entity) = q_player.single_mut();
 
-    if combat_state.opponent.is_none() {
-        return;
-    }
-
-    for _ in ev_combat.read() {
-        let (mut player_health, _, player_combat, player_entity) = q_player.single_mut();
-        if !player_combat.is_in_combat {
-            return;
+    for attack in ev_combat.read() {
+        if Some(attack.0) != combat_state.opponent || !player_combat.is_in_combat {
+            continue;
         }
-
-        let Ok((_, mut enemy_animation)) = q_enemies.get_mut(combat_state.opponent.unwrap()) else {
-            return;
+        let Ok((enemy_health, enemy_location, mut enemy_animation, mut enemy_weapon)) =
+            q_enemies.get_mut(attack.0)
+        else {
+            continue;
         };
+        // The player may have killed it earlier in the same tick.
+        if enemy_health.0 <= 0 {
+            continue;
+        }
+        let Some(weapon) = weapons.get(&enemy_weapon.def) else {
+            continue;
+        };
+        // The player may be walking away from a fight they can't win.
+        if !in_range(
+            &world,
+            enemy_location.get_location(),
+            player_location.get_location(),
+            weapon.range,
+        ) {
+            continue;
+        }
+        start_cooldown(&mut combat_state, weapon);
+        let damage = match enemy_weapon.fire(weapon, rng.as_mut()) {
+            Shot::Hit(damage) => damage,
+            Shot::Miss => 0,
+            Shot::OutOfAmmo => continue,
+        };
+
         enemy_animation.play("attack".to_string(), Some("walk".to_string()));
-        player_health.0 -= 30;
+        play_sound(&mut commands, weapon.fire_sound.clone());
+        if damage == 0 {
+            continue;
+        }
+        player_health.0 -= damage;
         ev_damaged.send(DamagedEvent(player_entity));
-        commands.spawn((
-            AudioPlayer(asset_server.load::<AudioSource>("sounds/weapon/shot_fire.wav")),
-            PlaybackSettings {
-                mode: bevy::audio::PlaybackMode::Despawn,
-                ..Default::default()
-            },
-        ));
+        if let Some(hit_sound) = &weapon.hit_sound {
+            play_sound(&mut commands, hit_sound.clone());
+        }
     }
 }
 
@@ -257,6 +380,8 @@ pub fn despawn_dead_enemies(
     mut sprite_params: Sprite3dParams,
     animations: Res<Animations>,
     mut combat_state: ResMut<CombatState>,
+    mut occupancy: ResMut<Occupancy>,
+    mut rng: ResMut<GameRng>,
 ) {
     if combat_state.opponent.is_some() {
         combat_state.opponent = if q_enemies.get(combat_state.opponent.unwrap()).is_err() {
@@ -282,14 +407,9 @@ pub fn despawn_dead_enemies(
         };
 
         commands.entity(entity).despawn();
-        if let Some(death_sound) = death_sound.pick() {
-            commands.spawn((
-                AudioPlayer(death_sound.clone()),
-                PlaybackSettings {
-                    mode: bevy::audio::PlaybackMode::Despawn,
-                    ..default()
-                },
-            ));
+        occupancy.remove(entity);
+        if let Some(death_sound) = death_sound.pick(rng.as_mut()) {
+            play_sound(&mut commands, death_sound.clone());
         }
         commands.spawn((
             Sprite3dBuilder {
@@ -301,6 +421,7 @@ pub fn despawn_dead_enemies(
             }
             .bundle_with_atlas(&mut sprite_params, texture_atlas),
             Billboard,
+            FloorEntity,
             AnimationTimer {
                 current_animation: "death".to_string(),
                 library: animation.library.clone(),
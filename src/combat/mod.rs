@@ -1,3 +1,5 @@
 pub mod combat;
 pub mod death;
 pub mod pain;
+pub mod regeneration;
+pub mod weapon;
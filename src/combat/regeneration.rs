@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+
+use crate::tick::tick::TurnEvent;
+
+use super::combat::{Combat, Health, MaxHealth};
+
+/// Heals `amount` every `interval` turns while out of combat.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Regeneration {
+    pub interval: u64,
+    pub amount: i32,
+}
+
+impl Default for Regeneration {
+    fn default() -> Self {
+        Regeneration {
+            interval: 5,
+            amount: 1,
+        }
+    }
+}
+
+pub fn regenerate(
+    mut ev_turn: EventReader<TurnEvent>,
+    mut q_actors: Query<(&mut Health, &MaxHealth, &Regeneration, &Combat)>,
+) {
+    for turn in ev_turn.read() {
+        let Ok((mut health, max_health, regeneration, combat)) = q_actors.get_mut(turn.actor)
+        else {
+            continue;
+        };
+        if combat.is_in_combat || turn.turn % regeneration.interval.max(1) != 0 {
+            continue;
+        }
+        health.0 = (health.0 + regeneration.amount).min(max_health.0);
+    }
+}
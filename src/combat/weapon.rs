@@ -0,0 +1,180 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use rand::Rng;
+use serde::Deserialize;
+
+/// How a weapon file is written, sounds given as asset paths. Ammo and the hit sound can be
+/// left out.
+#[derive(Deserialize)]
+struct WeaponFile {
+    name: String,
+    damage: (i32, i32),
+    accuracy: f64,
+    cooldown: f32,
+    range: i32,
+    #[serde(default)]
+    ammo: Option<u32>,
+    #[serde(default = "default_ammo_use")]
+    ammo_use: u32,
+    fire_sound: String,
+    #[serde(default)]
+    hit_sound: Option<String>,
+}
+
+fn default_ammo_use() -> u32 {
+    1
+}
+
+#[derive(Asset, TypePath, Debug)]
+pub struct WeaponDef {
+    pub name: String,
+    /// Lowest and highest damage of a hit, both included.
+    pub damage: (i32, i32),
+    /// Chance for a shot to hit.
+    pub accuracy: f64,
+    /// Seconds before the opponent gets to answer an attack.
+    pub cooldown: f32,
+    /// How many cells away an opponent can be hit from.
+    pub range: i32,
+    /// Rounds the weapon comes with, `None` if it never runs out.
+    pub ammo: Option<u32>,
+    pub ammo_use: u32,
+    #[dependency]
+    pub fire_sound: Handle<AudioSource>,
+    #[dependency]
+    pub hit_sound: Option<Handle<AudioSource>>,
+}
+
+#[derive(Debug)]
+pub enum WeaponLoadError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for WeaponLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeaponLoadError::Io(err) => write!(f, "couldn't read weapon: {err}"),
+            WeaponLoadError::Parse(err) => write!(f, "invalid weapon: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for WeaponLoadError {}
+
+impl From<std::io::Error> for WeaponLoadError {
+    fn from(err: std::io::Error) -> Self {
+        WeaponLoadError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for WeaponLoadError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        WeaponLoadError::Parse(err)
+    }
+}
+
+#[derive(Default)]
+pub struct WeaponLoader;
+
+impl AssetLoader for WeaponLoader {
+    type Asset = WeaponDef;
+    type Settings = ();
+    type Error = WeaponLoadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file = ron::de::from_bytes::<WeaponFile>(&bytes)?;
+
+        Ok(WeaponDef {
+            name: file.name,
+            damage: file.damage,
+            accuracy: file.accuracy,
+            cooldown: file.cooldown,
+            range: file.range,
+            ammo: file.ammo,
+            ammo_use: file.ammo_use,
+            fire_sound: load_context.load(file.fire_sound),
+            hit_sound: file.hit_sound.map(|path| load_context.load(path)),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapon.ron"]
+    }
+}
+
+pub const PLAYER_WEAPON: &str = "weapons/pistol.weapon.ron";
+pub const CULTIST_WEAPON: &str = "weapons/cultist.weapon.ron";
+
+pub enum Shot {
+    Hit(i32),
+    Miss,
+    OutOfAmmo,
+}
+
+/// The weapon a character fights with.
+#[derive(Component, Debug, Clone)]
+pub struct Weapon {
+    pub def: Handle<WeaponDef>,
+    /// Rounds used up so far, counted against [`WeaponDef::ammo`].
+    pub spent: u32,
+}
+
+impl Weapon {
+    pub fn new(def: Handle<WeaponDef>) -> Self {
+        Weapon { def, spent: 0 }
+    }
+
+    pub fn has_ammo(&self, def: &WeaponDef) -> bool {
+        def.ammo
+            .is_none_or(|ammo| self.spent + def.ammo_use <= ammo)
+    }
+
+    pub fn fire(&mut self, def: &WeaponDef, rng: &mut impl Rng) -> Shot {
+        if !self.has_ammo(def) {
+            return Shot::OutOfAmmo;
+        }
+        self.spent += def.ammo_use;
+
+        if !rng.gen_bool(def.accuracy.clamp(0.0, 1.0)) {
+            return Shot::Miss;
+        }
+        let (min, max) = def.damage;
+        Shot::Hit(rng.gen_range(min.min(max)..=max.max(min)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weapon_files_leave_out_ammo_and_hit_sound() {
+        let file = ron::de::from_str::<WeaponFile>(
+            r#"(
+                name: "Knife",
+                damage: (5, 10),
+                accuracy: 1.0,
+                cooldown: 0.5,
+                range: 1,
+                fire_sound: "sounds/weapon/shot_fire.wav",
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(file.ammo, None);
+        assert_eq!(file.ammo_use, 1);
+        assert_eq!(file.hit_sound, None);
+    }
+}
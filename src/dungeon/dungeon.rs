@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+
+use crate::{
+    characters::{
+        enemy::enemy::Enemy,
+        facing::Facing,
+        location::WorldLocation,
+        occupancy::Occupancy,
+        player::{player::Player, travel::Travel},
+    },
+    combat::{
+        combat::{CombatState, Health},
+        weapon::Weapon,
+    },
+    gen::{
+        generator::{generate_floor, GeneratorConfig},
+        location::WorldCatacomb,
+        prefab::PrefabLibrary,
+        theme::ThemeConfig,
+        tile::TileKind,
+        walker::WalkerConfig,
+    },
+    input::input::{ActionState, InputAction},
+    rng::rng::GameRng,
+    state::GameState,
+    tick::tick::{Energy, Speed},
+    utils::utils::convert_ivec2_to_vec3_plane,
+    CAMERA_HEIGHT, F32_ROOM_SIZE,
+};
+
+const EXPLORE_RADIUS: i32 = 2;
+
+/// Marks entities that belong to the current floor and get despawned when leaving it.
+#[derive(Component, Default)]
+pub struct FloorEntity;
+
+pub struct SavedEnemy {
+    pub location: IVec2,
+    pub forward: Facing,
+    pub health: i32,
+    pub speed: Speed,
+    pub energy: Energy,
+    pub weapon: Weapon,
+}
+
+pub struct SavedFloor {
+    pub catacomb: WorldCatacomb,
+    pub enemies: Vec<SavedEnemy>,
+}
+
+#[derive(Resource, Default)]
+pub struct Dungeon {
+    floors: Vec<SavedFloor>,
+    depth: usize,
+    /// Stairs the player arrives on when entering the current floor.
+    arrival: Option<TileKind>,
+    last_cell: Option<IVec2>,
+}
+
+impl Dungeon {
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn current_floor(&self) -> Option<&SavedFloor> {
+        self.floors.get(self.depth)
+    }
+
+    fn save_floor(&mut self, floor: SavedFloor) {
+        if self.depth < self.floors.len() {
+            self.floors[self.depth] = floor;
+        } else {
+            self.floors.push(floor);
+        }
+    }
+}
+
+pub fn enter_floor(
+    mut location: ResMut<WorldCatacomb>,
+    dungeon: Res<Dungeon>,
+    generators: Res<GeneratorConfig>,
+    walker_config: Res<WalkerConfig>,
+    prefabs: Res<PrefabLibrary>,
+    themes: Res<ThemeConfig>,
+    rng: Res<GameRng>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    let depth = dungeon.depth();
+
+    *location = match dungeon.current_floor() {
+        Some(floor) => {
+            info!("Restoring floor {depth}...");
+            floor.catacomb.clone()
+        }
+        None => generate_floor(
+            depth,
+            &generators,
+            &walker_config,
+            &prefabs.0,
+            &themes,
+            &mut rng.for_floor(depth),
+        ),
+    };
+
+    info!("Done generating {} cells...", location.len());
+    state.set(GameState::Game);
+}
+
+pub fn place_player(
+    location: Res<WorldCatacomb>,
+    mut dungeon: ResMut<Dungeon>,
+    mut occupancy: ResMut<Occupancy>,
+    mut q_player: Query<(Entity, &mut WorldLocation, Option<&mut Transform>), With<Player>>,
+) {
+    // Floors are centered on the cell they were generated from.
+    let start = IVec2::ZERO;
+    let cell = match dungeon.arrival {
+        Some(stairs) => location.find_kind(stairs).unwrap_or_else(|| {
+            warn!(
+                "Floor {} has no {stairs:?}, starting at the floor's start.",
+                dungeon.depth
+            );
+            start
+        }),
+        None => start,
+    };
+
+    let (player, mut player_location, transform) = q_player.single_mut();
+    player_location.set_location(cell);
+    occupancy.set(player, cell);
+    dungeon.last_cell = Some(cell);
+
+    // Snap the camera instead of lerping it across the floor.
+    if let Some(mut transform) = transform {
+        transform.translation = convert_ivec2_to_vec3_plane(cell) * F32_ROOM_SIZE;
+        transform.translation.y = CAMERA_HEIGHT;
+    }
+}
+
+pub fn explore_cells(
+    mut location: ResMut<WorldCatacomb>,
+    q_player: Query<&WorldLocation, (With<Player>, Changed<WorldLocation>)>,
+) {
+    let Ok(player_location) = q_player.get_single() else {
+        return;
+    };
+    let center = player_location.get_location();
+
+    for i in -EXPLORE_RADIUS..=EXPLORE_RADIUS {
+        for j in -EXPLORE_RADIUS..=EXPLORE_RADIUS {
+            if let Some(tile) = location.get_mut(center + IVec2::new(i, j)) {
+                tile.meta.explored = true;
+            }
+        }
+    }
+}
+
+pub fn use_stairs(
+    mut commands: Commands,
+    mut dungeon: ResMut<Dungeon>,
+    mut combat_state: ResMut<CombatState>,
+    mut state: ResMut<NextState<GameState>>,
+    location: Res<WorldCatacomb>,
+    actions: Res<ActionState>,
+    mut travel: ResMut<Travel>,
+    mut occupancy: ResMut<Occupancy>,
+    q_player: Query<&WorldLocation, With<Player>>,
+    q_enemies: Query<(&WorldLocation, &Health, &Speed, &Energy, &Weapon), With<Enemy>>,
+    q_floor_entities: Query<Entity, With<FloorEntity>>,
+) {
+    let cell = q_player.single().get_location();
+    // Stairs are taken when stepping onto them, or on Interact while standing on them.
+    if dungeon.last_cell == Some(cell) && !actions.just_pressed(InputAction::Interact) {
+        return;
+    }
+    dungeon.last_cell = Some(cell);
+    // Travelling over stairs doesn't take them unless they are where the player is headed.
+    if travel.is_passing(cell) {
+        return;
+    }
+
+    let (depth, arrival) = match location.kind(cell) {
+        Some(TileKind::StairsDown) => (dungeon.depth + 1, TileKind::StairsUp),
+        Some(TileKind::StairsUp) if dungeon.depth > 0 => (dungeon.depth - 1, TileKind::StairsDown),
+        _ => return,
+    };
+
+    info!("Taking the stairs to floor {depth}...");
+    travel.stop();
+
+    let enemies = q_enemies
+        .iter()
+        .filter(|(_, health, _, _, _)| health.0 > 0)
+        .map(
+            |(enemy_location, health, speed, energy, weapon)| SavedEnemy {
+                location: enemy_location.get_location(),
+                forward: enemy_location.get_facing(),
+                health: health.0,
+                speed: *speed,
+                energy: *energy,
+                weapon: weapon.clone(),
+            },
+        )
+        .collect();
+    dungeon.save_floor(SavedFloor {
+        catacomb: location.clone(),
+        enemies,
+    });
+
+    for entity in q_floor_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    // The player claims their cell again once they arrive.
+    occupancy.clear();
+    combat_state.opponent = None;
+
+    dungeon.depth = depth;
+    dungeon.arrival = Some(arrival);
+    state.set(GameState::Generating);
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+
+use super::location::WorldCatacomb;
+use super::tile::{Tile, TileKind};
+
+/// A hand-authored floor, written as a grid of characters:
+///
+/// - `#` or space: wall
+/// - `.`: floor
+/// - `@`: player start
+/// - `E`: enemy spawn
+/// - `<` and `>`: stairs up and down
+/// - `+`: door, `~`: water, `^`: pit, `*`: secret passage
+///
+/// The first line is the northern edge of the map.
+#[derive(Asset, TypePath, Clone)]
+pub struct AsciiMap(pub WorldCatacomb);
+
+#[derive(Debug)]
+pub enum AsciiMapError {
+    Io(std::io::Error),
+    UnknownCharacter { character: char, line: usize },
+    MissingStart,
+}
+
+impl fmt::Display for AsciiMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiMapError::Io(err) => write!(f, "couldn't read map: {err}"),
+            AsciiMapError::UnknownCharacter { character, line } => {
+                write!(f, "unknown character '{character}' on line {line}")
+            }
+            AsciiMapError::MissingStart => write!(f, "map has no player start '@'"),
+        }
+    }
+}
+
+impl std::error::Error for AsciiMapError {}
+
+impl From<std::io::Error> for AsciiMapError {
+    fn from(err: std::io::Error) -> Self {
+        AsciiMapError::Io(err)
+    }
+}
+
+pub fn tile_for_char(character: char) -> Option<Tile> {
+    let kind = match character {
+        '.' | '@' | 'E' => TileKind::Floor,
+        '<' => TileKind::StairsUp,
+        '>' => TileKind::StairsDown,
+        '+' => TileKind::Door,
+        '~' => TileKind::Water,
+        '^' => TileKind::Pit,
+        '*' => TileKind::SecretPassage,
+        _ => return None,
+    };
+    Some(Tile::new(kind))
+}
+
+pub fn char_for_tile(kind: TileKind) -> char {
+    match kind {
+        TileKind::Floor => '.',
+        TileKind::StairsUp => '<',
+        TileKind::StairsDown => '>',
+        TileKind::Door => '+',
+        TileKind::Water => '~',
+        TileKind::Pit => '^',
+        TileKind::SecretPassage => '*',
+    }
+}
+
+/// Draws the catacomb in the same format [`AsciiMap::parse`] reads, with a wall border.
+pub fn to_ascii(location: &WorldCatacomb) -> String {
+    let cells = location.sorted_cells();
+    let min = cells.iter().fold(IVec2::ZERO, |min, cell| min.min(*cell)) - IVec2::ONE;
+    let max = cells.iter().fold(IVec2::ZERO, |max, cell| max.max(*cell)) + IVec2::ONE;
+
+    let mut text = String::new();
+    for y in (min.y..=max.y).rev() {
+        for x in min.x..=max.x {
+            let cell = IVec2::new(x, y);
+            // Stairs up also mark the start of deeper floors, they keep their own glyph.
+            text.push(
+                if cell == IVec2::ZERO && location.kind(cell) == Some(TileKind::Floor) {
+                    '@'
+                } else if location.spawns().contains(&cell) {
+                    'E'
+                } else {
+                    location.kind(cell).map_or('#', char_for_tile)
+                },
+            );
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Yields every character of the grid with its cell, the first line being the northern edge.
+pub fn grid_cells(text: &str) -> impl Iterator<Item = (usize, IVec2, char)> + '_ {
+    let lines = text.lines().collect::<Vec<_>>();
+    let height = lines.len() as i32;
+
+    lines.into_iter().enumerate().flat_map(move |(row, line)| {
+        line.chars().enumerate().map(move |(column, character)| {
+            (
+                row + 1,
+                IVec2::new(column as i32, height - 1 - row as i32),
+                character,
+            )
+        })
+    })
+}
+
+impl AsciiMap {
+    pub fn parse(text: &str) -> Result<Self, AsciiMapError> {
+        let mut location = WorldCatacomb::default();
+        let mut start = None;
+
+        for (line, cell, character) in grid_cells(text) {
+            match character {
+                '#' | ' ' => continue,
+                '@' => start = Some(cell),
+                'E' => location.add_spawn(cell),
+                _ => {}
+            }
+
+            let Some(tile) = tile_for_char(character) else {
+                return Err(AsciiMapError::UnknownCharacter { character, line });
+            };
+            location.insert(cell, tile);
+        }
+
+        let start = start.ok_or(AsciiMapError::MissingStart)?;
+        Ok(AsciiMap(location.centered_on(start)))
+    }
+}
+
+#[derive(Default)]
+pub struct AsciiMapLoader;
+
+impl AssetLoader for AsciiMapLoader {
+    type Asset = AsciiMap;
+    type Settings = ();
+    type Error = AsciiMapError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        AsciiMap::parse(&String::from_utf8_lossy(&bytes))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map"]
+    }
+}
+
+#[derive(Resource)]
+pub struct CustomMapPath(pub String);
+
+#[derive(Resource)]
+pub struct CustomMap(pub Handle<AsciiMap>);
+
+pub fn load_custom_map(
+    custom_map: Option<Res<CustomMap>>,
+    maps: Res<Assets<AsciiMap>>,
+    mut location: ResMut<WorldCatacomb>,
+) {
+    let Some(custom_map) = custom_map else {
+        return;
+    };
+    let Some(map) = maps.get(&custom_map.0) else {
+        return;
+    };
+
+    info!(
+        "Starting in a hand-authored map with {} cells...",
+        map.0.len()
+    );
+    *location = map.0.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_to_ascii_round_trip() {
+        let text = "\
+#######
+#.@..E#
+#.#~#.#
+#+..^>#
+#######
+";
+        let AsciiMap(location) = AsciiMap::parse(text).unwrap();
+
+        assert_eq!(location.kind(IVec2::ZERO), Some(TileKind::Floor));
+        assert_eq!(location.kind(IVec2::new(0, -1)), None);
+        assert_eq!(location.kind(IVec2::new(3, -2)), Some(TileKind::StairsDown));
+        assert_eq!(location.spawns(), &[IVec2::new(3, 0)]);
+        assert_eq!(to_ascii(&location), text);
+    }
+
+    #[test]
+    fn parse_rejects_bad_maps() {
+        assert!(matches!(
+            AsciiMap::parse("#..#"),
+            Err(AsciiMapError::MissingStart)
+        ));
+        assert!(matches!(
+            AsciiMap::parse("#@?#"),
+            Err(AsciiMapError::UnknownCharacter {
+                character: '?',
+                line: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn to_ascii_keeps_stairs_at_start() {
+        let mut location = WorldCatacomb::default();
+        location.set_kind(IVec2::ZERO, TileKind::StairsUp);
+        location.dig(IVec2::X);
+
+        assert_eq!(to_ascii(&location), "####\n#<.#\n####\n");
+    }
+}
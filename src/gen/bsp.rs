@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::generator::DungeonGenerator;
+use super::location::WorldCatacomb;
+use super::tile::{Tile, TileKind};
+use crate::rng::rng::GameRng;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BspConfig {
+    pub width: i32,
+    pub height: i32,
+    /// Areas smaller than twice this size are not split any further.
+    pub min_leaf_size: i32,
+    pub min_room_size: i32,
+    /// Solid cells kept between a room and the edge of its area.
+    pub room_padding: i32,
+}
+
+impl Default for BspConfig {
+    fn default() -> Self {
+        BspConfig {
+            width: 48,
+            height: 32,
+            min_leaf_size: 8,
+            min_room_size: 3,
+            room_padding: 1,
+        }
+    }
+}
+
+pub struct BspGenerator<'a>(pub &'a BspConfig);
+
+impl BspGenerator<'_> {
+    fn split(&self, area: IRect, rng: &mut GameRng, location: &mut WorldCatacomb) -> IRect {
+        let config = self.0;
+        let min_leaf = config
+            .min_leaf_size
+            .max(config.min_room_size + config.room_padding * 2)
+            .max(1);
+
+        let can_split_x = area.width() >= min_leaf * 2;
+        let can_split_y = area.height() >= min_leaf * 2;
+
+        if !can_split_x && !can_split_y {
+            return self.carve_room(area, rng, location);
+        }
+
+        let split_x = match (can_split_x, can_split_y) {
+            (true, false) => true,
+            (false, true) => false,
+            _ if area.width() > area.height() * 3 / 2 => true,
+            _ if area.height() > area.width() * 3 / 2 => false,
+            _ => rng.gen_bool(0.5),
+        };
+
+        let (first, second) = if split_x {
+            let x = area.min.x + rng.gen_range(min_leaf..=area.width() - min_leaf);
+            (
+                IRect::new(area.min.x, area.min.y, x, area.max.y),
+                IRect::new(x, area.min.y, area.max.x, area.max.y),
+            )
+        } else {
+            let y = area.min.y + rng.gen_range(min_leaf..=area.height() - min_leaf);
+            (
+                IRect::new(area.min.x, area.min.y, area.max.x, y),
+                IRect::new(area.min.x, y, area.max.x, area.max.y),
+            )
+        };
+
+        let first = self.split(first, rng, location);
+        let second = self.split(second, rng, location);
+        carve_corridor(first.center(), second.center(), rng, location);
+
+        if rng.gen_bool(0.5) {
+            first
+        } else {
+            second
+        }
+    }
+
+    fn carve_room(&self, area: IRect, rng: &mut GameRng, location: &mut WorldCatacomb) -> IRect {
+        let config = self.0;
+        let max_size = area.size() - IVec2::splat(config.room_padding * 2);
+        let min_size = IVec2::splat(config.min_room_size)
+            .min(max_size)
+            .max(IVec2::ONE);
+
+        let size = IVec2::new(
+            rng.gen_range(min_size.x..=max_size.x.max(min_size.x)),
+            rng.gen_range(min_size.y..=max_size.y.max(min_size.y)),
+        );
+        let free = (area.size() - size - IVec2::splat(config.room_padding * 2)).max(IVec2::ZERO);
+        let min = area.min
+            + IVec2::splat(config.room_padding)
+            + IVec2::new(rng.gen_range(0..=free.x), rng.gen_range(0..=free.y));
+        let room = IRect::from_corners(min, min + size);
+        // Regions start at one, zero is reserved for corridors.
+        let region = location
+            .iter()
+            .map(|(_, tile)| tile.meta.region)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        for x in room.min.x..room.max.x {
+            for y in room.min.y..room.max.y {
+                location.insert(
+                    IVec2::new(x, y),
+                    Tile::new(TileKind::Floor).with_region(region),
+                );
+            }
+        }
+
+        room
+    }
+}
+
+fn carve_corridor(from: IVec2, to: IVec2, rng: &mut GameRng, location: &mut WorldCatacomb) {
+    let corner = if rng.gen_bool(0.5) {
+        IVec2::new(to.x, from.y)
+    } else {
+        IVec2::new(from.x, to.y)
+    };
+
+    for (start, end) in [(from, corner), (corner, to)] {
+        let step = (end - start).signum();
+        let mut cell = start;
+        location.dig(cell);
+        while cell != end {
+            cell += step;
+            location.dig(cell);
+        }
+    }
+}
+
+impl DungeonGenerator for BspGenerator<'_> {
+    fn generate(&self, rng: &mut GameRng) -> WorldCatacomb {
+        let config = self.0;
+        let mut location = WorldCatacomb::default();
+        let area = IRect::new(0, 0, config.width, config.height);
+
+        let start_room = self.split(area, rng, &mut location);
+
+        location.centered_on(start_room.center())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(seed: u64, config: &BspConfig) -> WorldCatacomb {
+        BspGenerator(config).generate(&mut GameRng::from_seed(seed))
+    }
+
+    #[test]
+    fn rooms_connect_to_the_start() {
+        let config = BspConfig::default();
+
+        for seed in 0..10 {
+            let location = generate(seed, &config);
+            assert!(location.iter().any(|(_, tile)| tile.meta.region > 1));
+            assert_eq!(location.distances_from(IVec2::ZERO).len(), location.len());
+        }
+    }
+
+    #[test]
+    fn zero_sizes_still_carve_rooms() {
+        let config = BspConfig {
+            width: 12,
+            height: 12,
+            min_leaf_size: 0,
+            min_room_size: 0,
+            room_padding: 0,
+        };
+
+        assert_ne!(generate(3, &config).len(), 0);
+    }
+}
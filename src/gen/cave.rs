@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::generator::DungeonGenerator;
+use super::location::WorldCatacomb;
+use crate::rng::rng::GameRng;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CaveConfig {
+    pub width: i32,
+    pub height: i32,
+    /// Chance for a cell to start out as a wall.
+    pub fill_chance: f64,
+    pub iterations: u32,
+    /// An open cell turns into a wall with at least this many wall neighbours.
+    pub wall_birth: usize,
+    /// A wall stays a wall with at least this many wall neighbours.
+    pub wall_survival: usize,
+}
+
+impl Default for CaveConfig {
+    fn default() -> Self {
+        CaveConfig {
+            width: 48,
+            height: 32,
+            fill_chance: 0.45,
+            iterations: 5,
+            wall_birth: 5,
+            wall_survival: 4,
+        }
+    }
+}
+
+pub struct CaveGenerator<'a>(pub &'a CaveConfig);
+
+struct Grid {
+    width: i32,
+    height: i32,
+    walls: Vec<bool>,
+}
+
+impl Grid {
+    fn is_wall(&self, cell: IVec2) -> bool {
+        if cell.x <= 0 || cell.y <= 0 || cell.x >= self.width - 1 || cell.y >= self.height - 1 {
+            return true;
+        }
+        self.walls[(cell.y * self.width + cell.x) as usize]
+    }
+
+    fn wall_neighbours(&self, cell: IVec2) -> usize {
+        let mut count = 0;
+        for i in -1..2 {
+            for j in -1..2 {
+                if (i != 0 || j != 0) && self.is_wall(cell + IVec2::new(i, j)) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    fn cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| IVec2::new(x, y)))
+    }
+
+    /// Flood fills every open region and returns the largest one.
+    fn largest_region(&self) -> Vec<IVec2> {
+        let mut visited = vec![false; self.walls.len()];
+        let mut largest = Vec::new();
+
+        for cell in self.cells() {
+            let index = (cell.y * self.width + cell.x) as usize;
+            if visited[index] || self.is_wall(cell) {
+                continue;
+            }
+
+            let mut region = Vec::new();
+            let mut stack = vec![cell];
+            visited[index] = true;
+            while let Some(cell) = stack.pop() {
+                region.push(cell);
+                for dir in [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y] {
+                    let next = cell + dir;
+                    if self.is_wall(next) {
+                        continue;
+                    }
+                    let next_index = (next.y * self.width + next.x) as usize;
+                    if !visited[next_index] {
+                        visited[next_index] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+
+            if region.len() > largest.len() {
+                largest = region;
+            }
+        }
+
+        largest
+    }
+}
+
+impl DungeonGenerator for CaveGenerator<'_> {
+    fn generate(&self, rng: &mut GameRng) -> WorldCatacomb {
+        let config = self.0;
+        let width = config.width.max(3);
+        let height = config.height.max(3);
+        let mut grid = Grid {
+            width,
+            height,
+            walls: (0..width * height)
+                .map(|_| rng.gen_bool(config.fill_chance.clamp(0.0, 1.0)))
+                .collect(),
+        };
+
+        for _ in 0..config.iterations {
+            let walls = grid
+                .cells()
+                .map(|cell| {
+                    let neighbours = grid.wall_neighbours(cell);
+                    if grid.is_wall(cell) {
+                        neighbours >= config.wall_survival
+                    } else {
+                        neighbours >= config.wall_birth
+                    }
+                })
+                .collect();
+            grid.walls = walls;
+        }
+
+        let region = grid.largest_region();
+        let center = IVec2::new(width / 2, height / 2);
+        let Some(start) = region
+            .iter()
+            .min_by_key(|cell| (**cell - center).length_squared())
+            .copied()
+        else {
+            warn!("Cave generator produced no open cells.");
+            let mut location = WorldCatacomb::default();
+            location.dig(IVec2::ZERO);
+            return location;
+        };
+
+        let mut location = WorldCatacomb::default();
+        for cell in region {
+            location.dig(cell);
+        }
+        location.centered_on(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_cave_around_the_start() {
+        let config = CaveConfig::default();
+
+        for seed in 0..10 {
+            let location = CaveGenerator(&config).generate(&mut GameRng::from_seed(seed));
+            assert!(location.kind(IVec2::ZERO).is_some());
+            assert_eq!(location.distances_from(IVec2::ZERO).len(), location.len());
+            assert!(location.len() < (config.width * config.height) as usize);
+        }
+    }
+
+    #[test]
+    fn solid_caves_fall_back_to_a_single_cell() {
+        let config = CaveConfig {
+            fill_chance: 1.0,
+            ..default()
+        };
+
+        let location = CaveGenerator(&config).generate(&mut GameRng::from_seed(0));
+        assert_eq!(location.sorted_cells(), [IVec2::ZERO]);
+    }
+}
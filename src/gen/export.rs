@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use super::generator::GeneratorKind;
+use super::location::WorldCatacomb;
+use super::tile::TileKind;
+use super::validate::LevelMetrics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Ascii,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ascii" => Ok(OutputFormat::Ascii),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("unknown format \"{s}\", expected ascii or json")),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ExportedCell {
+    pub x: i32,
+    pub y: i32,
+    pub kind: TileKind,
+    pub region: u32,
+    pub theme: u32,
+}
+
+/// A generated floor as written by the `generate` subcommand.
+#[derive(Serialize)]
+pub struct ExportedFloor {
+    pub seed: u64,
+    pub depth: usize,
+    pub generator: GeneratorKind,
+    pub start: [i32; 2],
+    pub cells: Vec<ExportedCell>,
+    pub spawns: Vec<[i32; 2]>,
+    /// Cell of each torch and the direction of the wall it hangs on.
+    pub torches: Vec<[[i32; 2]; 2]>,
+    pub metrics: LevelMetrics,
+}
+
+impl ExportedFloor {
+    pub fn new(
+        location: &WorldCatacomb,
+        seed: u64,
+        depth: usize,
+        generator: GeneratorKind,
+        metrics: LevelMetrics,
+    ) -> Self {
+        let cells = location
+            .sorted_cells()
+            .into_iter()
+            .filter_map(|cell| {
+                let tile = location.get(cell)?;
+                Some(ExportedCell {
+                    x: cell.x,
+                    y: cell.y,
+                    kind: tile.kind,
+                    region: tile.meta.region,
+                    theme: tile.meta.theme,
+                })
+            })
+            .collect();
+
+        ExportedFloor {
+            seed,
+            depth,
+            generator,
+            start: IVec2::ZERO.to_array(),
+            cells,
+            spawns: location
+                .spawns()
+                .iter()
+                .map(|cell| cell.to_array())
+                .collect(),
+            torches: location
+                .torches()
+                .iter()
+                .map(|torch| [torch.cell.to_array(), torch.wall.to_array()])
+                .collect(),
+            metrics,
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use super::bsp::{BspConfig, BspGenerator};
+use super::cave::{CaveConfig, CaveGenerator};
+use super::location::WorldCatacomb;
+use super::prefab::{place_prefabs, Prefab, PrefabConfig};
+use super::theme::{assign_themes, ThemeConfig};
+use super::tile::TileKind;
+use super::torch::place_torches;
+use super::validate::{LevelMetrics, ValidationConfig};
+use super::walker::{WalkerConfig, WalkerGenerator};
+use crate::rng::rng::GameRng;
+
+pub trait DungeonGenerator {
+    fn generate(&self, rng: &mut GameRng) -> WorldCatacomb;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorKind {
+    Walker,
+    Bsp,
+    Cave,
+}
+
+impl FromStr for GeneratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "walker" => Ok(GeneratorKind::Walker),
+            "bsp" => Ok(GeneratorKind::Bsp),
+            "cave" => Ok(GeneratorKind::Cave),
+            _ => Err(format!(
+                "unknown generator \"{s}\", expected walker, bsp or cave"
+            )),
+        }
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GeneratorConfig {
+    /// Generator used for each floor, repeating once the list runs out.
+    pub floors: Vec<GeneratorKind>,
+    pub bsp: BspConfig,
+    pub cave: CaveConfig,
+    pub validation: ValidationConfig,
+    pub prefabs: PrefabConfig,
+    pub enemies_per_floor: usize,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            floors: Vec::new(),
+            bsp: default(),
+            cave: default(),
+            validation: default(),
+            prefabs: default(),
+            enemies_per_floor: 4,
+        }
+    }
+}
+
+impl GeneratorConfig {
+    pub fn kind_for_floor(&self, depth: usize) -> GeneratorKind {
+        if self.floors.is_empty() {
+            return GeneratorKind::Walker;
+        }
+        self.floors[depth % self.floors.len()]
+    }
+}
+
+pub fn generate_floor(
+    depth: usize,
+    generators: &GeneratorConfig,
+    walker_config: &WalkerConfig,
+    prefabs: &[Prefab],
+    themes: &ThemeConfig,
+    rng: &mut GameRng,
+) -> WorldCatacomb {
+    let kind = generators.kind_for_floor(depth);
+    let validation = &generators.validation;
+    info!("Generating floor {depth} with {kind:?} generator...");
+
+    let mut attempt = 1;
+    let mut location = loop {
+        let mut location = match kind {
+            GeneratorKind::Walker => WalkerGenerator(walker_config).generate(rng),
+            GeneratorKind::Bsp => BspGenerator(&generators.bsp).generate(rng),
+            GeneratorKind::Cave => CaveGenerator(&generators.cave).generate(rng),
+        };
+        // Vaults are part of the floor that gets measured, their corridors can add dead ends.
+        place_prefabs(&mut location, prefabs, &generators.prefabs, rng);
+
+        let metrics = LevelMetrics::measure(&location, IVec2::ZERO, validation);
+        info!("Floor {depth} attempt {attempt}: {metrics:?}");
+
+        let failures = metrics.failures(validation);
+        if failures.is_empty() {
+            break location;
+        }
+        if attempt >= validation.max_attempts {
+            warn!(
+                "Floor {depth} still fails validation after {attempt} attempts ({}), using it anyway.",
+                failures.join(", ")
+            );
+            break location;
+        }
+
+        info!("Regenerating floor {depth}: {}.", failures.join(", "));
+        attempt += 1;
+    };
+
+    place_stairs(&mut location, depth);
+    place_spawns(&mut location, generators, rng);
+    assign_themes(&mut location, depth, themes, rng);
+    place_torches(&mut location, themes, rng);
+    location
+}
+
+fn place_spawns(location: &mut WorldCatacomb, generators: &GeneratorConfig, rng: &mut GameRng) {
+    let distances = location.distances_from(IVec2::ZERO);
+    let cells = location
+        .sorted_cells()
+        .into_iter()
+        .filter(|cell| {
+            location.kind(*cell) == Some(TileKind::Floor)
+                && !location.spawns().contains(cell)
+                && distances
+                    .get(cell)
+                    .is_some_and(|distance| *distance >= generators.validation.spawn_distance)
+        })
+        .collect::<Vec<_>>();
+
+    for cell in cells.choose_multiple(rng, generators.enemies_per_floor) {
+        location.add_spawn(*cell);
+    }
+}
+
+/// Puts the stairs up at the start and the stairs down as far away from it as possible, both on
+/// plain floor outside of vaults.
+fn place_stairs(location: &mut WorldCatacomb, depth: usize) {
+    let start = IVec2::ZERO;
+    let is_free = |location: &WorldCatacomb, cell| {
+        location.kind(cell) == Some(TileKind::Floor) && !location.in_vault(cell)
+    };
+    if depth > 0 {
+        if is_free(location, start) {
+            location.set_kind(start, TileKind::StairsUp);
+        } else {
+            warn!("Floor {depth} has no room for stairs up at the start.");
+        }
+    }
+
+    let distances = location.distances_from(start);
+    let exit = location
+        .sorted_cells()
+        .into_iter()
+        .filter(|cell| {
+            *cell != start && is_free(location, *cell) && !location.spawns().contains(cell)
+        })
+        .filter_map(|cell| Some((cell, *distances.get(&cell)?)))
+        .max_by_key(|(_, distance)| *distance);
+
+    match exit {
+        Some((exit, _)) => location.set_kind(exit, TileKind::StairsDown),
+        None => warn!("Floor {depth} is too small for stairs down."),
+    }
+}
@@ -1,4 +1,209 @@
-use bevy::{ecs::system::Resource, math::IVec2, utils::HashSet};
-
-#[derive(Resource, Default)]
-pub struct WorldCatacomb(pub HashSet<IVec2>);
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::system::Resource,
+    math::{IRect, IVec2},
+    utils::{HashMap, HashSet},
+};
+
+use super::tile::{Tile, TileKind};
+use super::torch::WallTorch;
+
+/// Width and height in cells of the pieces world geometry is built in.
+pub const CHUNK_SIZE: i32 = 16;
+
+#[derive(Resource, Default, Clone)]
+pub struct WorldCatacomb {
+    tiles: HashMap<IVec2, Tile>,
+    spawns: Vec<IVec2>,
+    torches: Vec<WallTorch>,
+    /// Areas taken up by prefab vaults.
+    vaults: Vec<IRect>,
+    /// Chunks whose geometry is out of date since the last rebuild.
+    dirty_chunks: HashSet<IVec2>,
+}
+
+impl WorldCatacomb {
+    pub fn get(&self, cell: IVec2) -> Option<&Tile> {
+        self.tiles.get(&cell)
+    }
+
+    pub fn get_mut(&mut self, cell: IVec2) -> Option<&mut Tile> {
+        self.tiles.get_mut(&cell)
+    }
+
+    pub fn kind(&self, cell: IVec2) -> Option<TileKind> {
+        self.get(cell).map(|tile| tile.kind)
+    }
+
+    pub fn insert(&mut self, cell: IVec2, tile: Tile) {
+        self.tiles.insert(cell, tile);
+        self.mark_dirty(cell);
+    }
+
+    pub fn set_kind(&mut self, cell: IVec2, kind: TileKind) {
+        self.tiles.entry(cell).or_default().kind = kind;
+        self.mark_dirty(cell);
+    }
+
+    /// Opens up a floor tile, returns `false` if the cell was already open.
+    pub fn dig(&mut self, cell: IVec2) -> bool {
+        if self.tiles.contains_key(&cell) {
+            return false;
+        }
+        self.tiles.insert(cell, Tile::default());
+        self.mark_dirty(cell);
+        true
+    }
+
+    pub fn chunk_of(cell: IVec2) -> IVec2 {
+        cell.div_euclid(IVec2::splat(CHUNK_SIZE))
+    }
+
+    /// Walls are built in the solid cells next to open ones, so neighbouring chunks change too.
+    fn mark_dirty(&mut self, cell: IVec2) {
+        for dir in [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y] {
+            self.dirty_chunks.insert(Self::chunk_of(cell + dir));
+        }
+    }
+
+    pub fn has_dirty_chunks(&self) -> bool {
+        !self.dirty_chunks.is_empty()
+    }
+
+    pub fn take_dirty_chunks(&mut self) -> HashSet<IVec2> {
+        std::mem::take(&mut self.dirty_chunks)
+    }
+
+    /// Every chunk that holds geometry, including the walls around the edge cells.
+    pub fn chunks(&self) -> HashSet<IVec2> {
+        self.cells()
+            .flat_map(|cell| {
+                [
+                    IVec2::ZERO,
+                    IVec2::ONE,
+                    IVec2::NEG_ONE,
+                    IVec2::new(1, -1),
+                    IVec2::new(-1, 1),
+                ]
+                .map(|offset| Self::chunk_of(cell + offset))
+            })
+            .collect()
+    }
+
+    pub fn is_passable(&self, cell: IVec2) -> bool {
+        self.get(cell).is_some_and(|tile| tile.kind.is_passable())
+    }
+
+    pub fn is_solid(&self, cell: IVec2) -> bool {
+        self.get(cell).is_none_or(|tile| tile.kind.is_solid())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, &Tile)> {
+        self.tiles.iter().map(|(cell, tile)| (*cell, tile))
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.tiles.keys().copied()
+    }
+
+    /// Cells in a stable order, so random picks are reproducible from a seed.
+    pub fn sorted_cells(&self) -> Vec<IVec2> {
+        let mut cells = self.cells().collect::<Vec<_>>();
+        cells.sort_by_key(|cell| (cell.x, cell.y));
+        cells
+    }
+
+    pub fn find_kind(&self, kind: TileKind) -> Option<IVec2> {
+        self.sorted_cells()
+            .into_iter()
+            .find(|cell| self.kind(*cell) == Some(kind))
+    }
+
+    /// Walking distance from `start` to every passable cell that can be reached.
+    pub fn distances_from(&self, start: IVec2) -> HashMap<IVec2, u32> {
+        let mut distances = HashMap::new();
+        if !self.is_passable(start) {
+            return distances;
+        }
+
+        let mut queue = VecDeque::from([start]);
+        distances.insert(start, 0);
+        while let Some(cell) = queue.pop_front() {
+            let distance = distances[&cell];
+            for dir in [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y] {
+                let next = cell + dir;
+                if self.is_passable(next) && !distances.contains_key(&next) {
+                    distances.insert(next, distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Whether a straight line between the centres of two cells only crosses open cells.
+    pub fn can_see(&self, from: IVec2, to: IVec2) -> bool {
+        let delta = to - from;
+        let steps = delta.abs().max_element();
+        (1..steps).all(|step| {
+            let cell = from.as_vec2() + delta.as_vec2() * step as f32 / steps as f32;
+            !self.is_solid(cell.round().as_ivec2())
+        })
+    }
+
+    /// Cells where enemies are spawned when the floor is entered for the first time.
+    pub fn spawns(&self) -> &[IVec2] {
+        &self.spawns
+    }
+
+    pub fn add_spawn(&mut self, cell: IVec2) {
+        self.spawns.push(cell);
+    }
+
+    pub fn torches(&self) -> &[WallTorch] {
+        &self.torches
+    }
+
+    pub fn add_torch(&mut self, torch: WallTorch) {
+        self.torches.push(torch);
+    }
+
+    pub fn add_vault(&mut self, vault: IRect) {
+        self.vaults.push(vault);
+    }
+
+    pub fn in_vault(&self, cell: IVec2) -> bool {
+        self.vaults.iter().any(|vault| vault.contains(cell))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Moves every tile so that `start` ends up at the origin, where the player spawns.
+    pub fn centered_on(self, start: IVec2) -> Self {
+        WorldCatacomb {
+            tiles: self
+                .tiles
+                .into_iter()
+                .map(|(cell, tile)| (cell - start, tile))
+                .collect(),
+            spawns: self.spawns.into_iter().map(|cell| cell - start).collect(),
+            torches: self
+                .torches
+                .into_iter()
+                .map(|torch| WallTorch {
+                    cell: torch.cell - start,
+                    ..torch
+                })
+                .collect(),
+            vaults: self
+                .vaults
+                .into_iter()
+                .map(|vault| IRect::from_corners(vault.min - start, vault.max - start))
+                .collect(),
+            dirty_chunks: HashSet::new(),
+        }
+    }
+}
@@ -1,2 +1,12 @@
+pub mod ascii;
+pub mod bsp;
+pub mod cave;
+pub mod export;
+pub mod generator;
 pub mod location;
+pub mod prefab;
+pub mod theme;
+pub mod tile;
+pub mod torch;
+pub mod validate;
 pub mod walker;
@@ -0,0 +1,317 @@
+use std::{fmt, fs, path::Path};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext, LoadedFolder},
+    prelude::*,
+    utils::HashSet,
+};
+use pathfinding::prelude::astar;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use super::ascii::{grid_cells, tile_for_char, AsciiMapError};
+use super::location::WorldCatacomb;
+use super::tile::Tile;
+use crate::rng::rng::GameRng;
+
+/// A hand-made set piece stamped into generated floors, written like an
+/// [`AsciiMap`](super::ascii::AsciiMap) without a player start. `C` marks a floor
+/// cell on the edge of the vault that gets connected to the rest of the catacomb.
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct Prefab {
+    tiles: Vec<(IVec2, Tile)>,
+    spawns: Vec<IVec2>,
+    connections: Vec<IVec2>,
+    size: IVec2,
+}
+
+#[derive(Debug)]
+pub enum PrefabError {
+    Map(AsciiMapError),
+    MissingConnection,
+}
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefabError::Map(err) => err.fmt(f),
+            PrefabError::MissingConnection => write!(f, "prefab has no connection 'C'"),
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+impl From<AsciiMapError> for PrefabError {
+    fn from(err: AsciiMapError) -> Self {
+        PrefabError::Map(err)
+    }
+}
+
+impl From<std::io::Error> for PrefabError {
+    fn from(err: std::io::Error) -> Self {
+        PrefabError::Map(AsciiMapError::Io(err))
+    }
+}
+
+impl Prefab {
+    pub fn parse(text: &str) -> Result<Self, PrefabError> {
+        let mut tiles = Vec::new();
+        let mut spawns = Vec::new();
+        let mut connections = Vec::new();
+        let mut size = IVec2::ZERO;
+
+        for (line, cell, character) in grid_cells(text) {
+            size = size.max(cell + IVec2::ONE);
+            let tile = match character {
+                '#' | ' ' => continue,
+                'C' => {
+                    connections.push(cell);
+                    tile_for_char('.')
+                }
+                'E' => {
+                    spawns.push(cell);
+                    tile_for_char(character)
+                }
+                '@' => None,
+                _ => tile_for_char(character),
+            };
+
+            let Some(tile) = tile else {
+                return Err(AsciiMapError::UnknownCharacter { character, line }.into());
+            };
+            tiles.push((cell, tile));
+        }
+
+        if connections.is_empty() {
+            return Err(PrefabError::MissingConnection);
+        }
+
+        Ok(Prefab {
+            tiles,
+            spawns,
+            connections,
+            size,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct PrefabLoader;
+
+impl AssetLoader for PrefabLoader {
+    type Asset = Prefab;
+    type Settings = ();
+    type Error = PrefabError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Prefab::parse(&String::from_utf8_lossy(&bytes))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vault"]
+    }
+}
+
+#[derive(Resource)]
+pub struct PrefabFolder(pub Handle<LoadedFolder>);
+
+#[derive(Resource, Default)]
+pub struct PrefabLibrary(pub Vec<Prefab>);
+
+impl PrefabLibrary {
+    /// Reads every vault in a folder without going through the asset server.
+    pub fn load_dir(path: &Path) -> Self {
+        let Ok(entries) = fs::read_dir(path) else {
+            warn!("Failed to open prefab folder \"{}\".", path.display());
+            return PrefabLibrary::default();
+        };
+
+        let mut paths = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "vault"))
+            .collect::<Vec<_>>();
+        // Same order as `collect_prefabs`, so a seed picks the same vaults in and out of the game.
+        paths.sort();
+
+        let prefabs = paths
+            .iter()
+            .filter_map(|path| {
+                let text = fs::read_to_string(path).ok()?;
+                match Prefab::parse(&text) {
+                    Ok(prefab) => Some(prefab),
+                    Err(err) => {
+                        warn!("Failed to parse prefab \"{}\": {}.", path.display(), err);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        PrefabLibrary(prefabs)
+    }
+}
+
+pub fn collect_prefabs(
+    mut commands: Commands,
+    folder: Res<PrefabFolder>,
+    folders: Res<Assets<LoadedFolder>>,
+    prefabs: Res<Assets<Prefab>>,
+) {
+    let Some(folder) = folders.get(&folder.0) else {
+        return;
+    };
+
+    let mut handles = folder
+        .handles
+        .iter()
+        .filter_map(|handle| {
+            Some((
+                handle.path()?.clone(),
+                handle.clone().try_typed::<Prefab>().ok()?,
+            ))
+        })
+        .collect::<Vec<_>>();
+    handles.sort_by(|(a, _), (b, _)| a.path().cmp(b.path()));
+
+    let library = handles
+        .iter()
+        .filter_map(|(_, handle)| prefabs.get(handle).cloned())
+        .collect::<Vec<_>>();
+
+    info!("Loaded {} prefabs.", library.len());
+    commands.insert_resource(PrefabLibrary(library));
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PrefabConfig {
+    pub per_floor: usize,
+    pub placement_attempts: u32,
+    /// How far from existing floor cells a vault may be placed.
+    pub max_distance: i32,
+}
+
+impl Default for PrefabConfig {
+    fn default() -> Self {
+        PrefabConfig {
+            per_floor: 1,
+            placement_attempts: 50,
+            max_distance: 6,
+        }
+    }
+}
+
+pub fn place_prefabs(
+    location: &mut WorldCatacomb,
+    prefabs: &[Prefab],
+    config: &PrefabConfig,
+    rng: &mut GameRng,
+) {
+    if prefabs.is_empty() {
+        return;
+    }
+
+    for _ in 0..config.per_floor {
+        let prefab = prefabs.choose(rng).unwrap();
+        let cells = location.sorted_cells();
+
+        let origin = (0..config.placement_attempts).find_map(|_| {
+            let anchor = *cells.choose(rng)?;
+            let offset = IVec2::new(
+                rng.gen_range(-config.max_distance..=config.max_distance),
+                rng.gen_range(-config.max_distance..=config.max_distance),
+            );
+            let origin = anchor + offset - prefab.size / 2;
+            fits(location, prefab, origin).then_some(origin)
+        });
+
+        let Some(origin) = origin else {
+            info!("Couldn't find room for a prefab.");
+            continue;
+        };
+
+        stamp(location, prefab, origin);
+    }
+}
+
+/// A vault needs solid rock under it and around it, so it doesn't merge into corridors.
+fn fits(location: &WorldCatacomb, prefab: &Prefab, origin: IVec2) -> bool {
+    for x in -1..=prefab.size.x {
+        for y in -1..=prefab.size.y {
+            if location.get(origin + IVec2::new(x, y)).is_some() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn stamp(location: &mut WorldCatacomb, prefab: &Prefab, origin: IVec2) {
+    let region = location
+        .iter()
+        .map(|(_, tile)| tile.meta.region)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let targets = location
+        .cells()
+        .filter(|cell| location.is_passable(*cell))
+        .collect::<HashSet<_>>();
+
+    for (cell, tile) in prefab.tiles.iter() {
+        location.insert(origin + *cell, tile.clone().with_region(region));
+    }
+    for spawn in prefab.spawns.iter() {
+        location.add_spawn(origin + *spawn);
+    }
+
+    let vault = IRect::from_corners(origin, origin + prefab.size - IVec2::ONE);
+    location.add_vault(vault);
+    for connection in prefab.connections.iter() {
+        connect(location, &targets, vault, origin + *connection);
+    }
+}
+
+/// Digs the shortest corridor from a vault entrance to the existing catacomb,
+/// going around the vault itself.
+fn connect(location: &mut WorldCatacomb, targets: &HashSet<IVec2>, vault: IRect, from: IVec2) {
+    let Some(target) = targets
+        .iter()
+        .min_by_key(|cell| ((**cell - from).abs().element_sum(), cell.x, cell.y))
+        .copied()
+    else {
+        return;
+    };
+
+    let bounds = IRect::from_corners(from.min(target), from.max(target)).inflate(4);
+    let path = astar(
+        &from,
+        |cell| {
+            [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y]
+                .into_iter()
+                .map(|dir| *cell + dir)
+                .filter(|next| bounds.contains(*next) && !vault.contains(*next))
+                .map(|next| (next, 1))
+                .collect::<Vec<_>>()
+        },
+        |cell| (target - *cell).abs().element_sum() as u32,
+        |cell| targets.contains(cell),
+    );
+
+    match path {
+        Some((path, _)) => {
+            for cell in path {
+                location.dig(cell);
+            }
+        }
+        None => warn!("Couldn't connect a prefab entrance at {from}."),
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::{prelude::*, utils::HashMap};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::location::WorldCatacomb;
+use crate::rng::rng::GameRng;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Surface {
+    Floor,
+    Ceiling,
+    Wall,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TextureVariant {
+    pub texture: String,
+    pub emission: Option<String>,
+    /// Multiplies the texture colour, in sRGB.
+    pub tint: [f32; 3],
+    pub weight: f32,
+}
+
+impl Default for TextureVariant {
+    fn default() -> Self {
+        TextureVariant {
+            texture: String::new(),
+            emission: None,
+            tint: [1.0; 3],
+            weight: 1.0,
+        }
+    }
+}
+
+impl TextureVariant {
+    fn new(texture: &str, emission: Option<&str>) -> Self {
+        TextureVariant {
+            texture: texture.to_string(),
+            emission: emission.map(str::to_string),
+            ..default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    pub floor: Vec<TextureVariant>,
+    pub ceiling: Vec<TextureVariant>,
+    pub wall: Vec<TextureVariant>,
+    /// Colour the fog fades to while standing in the theme, in sRGB.
+    pub fog_color: [f32; 3],
+    /// Chance for an open cell next to a wall to get a torch.
+    pub torch_density: f64,
+}
+
+impl Theme {
+    pub fn variants(&self, surface: Surface) -> &[TextureVariant] {
+        match surface {
+            Surface::Floor => &self.floor,
+            Surface::Ceiling => &self.ceiling,
+            Surface::Wall => &self.wall,
+        }
+    }
+
+    /// Turns a tile's variant roll into a weighted pick among the textures of a surface.
+    pub fn pick(&self, surface: Surface, roll: u32) -> Option<usize> {
+        let variants = self.variants(surface);
+        let total = variants.iter().map(|variant| variant.weight).sum::<f32>();
+        if total <= 0.0 {
+            return (!variants.is_empty()).then_some(0);
+        }
+
+        // Rotate per surface so a tile's floor and ceiling don't always get the same variant.
+        let roll = roll.rotate_left(surface as u32 * 11) as f32 / u32::MAX as f32 * total;
+        let mut sum = 0.0;
+        variants
+            .iter()
+            .position(|variant| {
+                sum += variant.weight;
+                roll < sum
+            })
+            .or(Some(variants.len() - 1))
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub themes: Vec<Theme>,
+    /// Theme names used for each floor, repeating once the list runs out.
+    pub floors: Vec<String>,
+    /// Chance for a room to get a random theme instead of the one of its floor.
+    pub room_theme_chance: f64,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            themes: vec![Theme {
+                name: "crypt".to_string(),
+                floor: vec![TextureVariant::new("textures/floor.png", None)],
+                ceiling: vec![TextureVariant::new("textures/floor.png", None)],
+                wall: vec![TextureVariant::new(
+                    "textures/wall.png",
+                    Some("textures/wall_emission.png"),
+                )],
+                fog_color: [0.0; 3],
+                torch_density: 0.05,
+            }],
+            floors: Vec::new(),
+            room_theme_chance: 0.0,
+        }
+    }
+}
+
+impl ThemeConfig {
+    pub fn theme(&self, index: u32) -> Option<&Theme> {
+        self.themes.get(index as usize)
+    }
+
+    pub fn theme_for_floor(&self, depth: usize) -> u32 {
+        if self.floors.is_empty() {
+            return 0;
+        }
+        let name = &self.floors[depth % self.floors.len()];
+        match self.themes.iter().position(|theme| theme.name == *name) {
+            Some(index) => index as u32,
+            None => {
+                warn!("Unknown theme \"{name}\", using the first one.");
+                0
+            }
+        }
+    }
+
+    /// Every texture any theme uses, so they can be loaded up front.
+    pub fn textures(&self) -> Vec<String> {
+        let mut textures = self
+            .themes
+            .iter()
+            .flat_map(|theme| theme.floor.iter().chain(&theme.ceiling).chain(&theme.wall))
+            .flat_map(|variant| std::iter::once(&variant.texture).chain(&variant.emission))
+            .cloned()
+            .collect::<Vec<_>>();
+        textures.sort();
+        textures.dedup();
+        textures
+    }
+}
+
+/// Gives every tile a theme, by floor or by room, and a roll used to pick its texture variants.
+pub fn assign_themes(
+    location: &mut WorldCatacomb,
+    depth: usize,
+    config: &ThemeConfig,
+    rng: &mut GameRng,
+) {
+    let floor_theme = config.theme_for_floor(depth);
+    let mut room_themes = HashMap::new();
+
+    for cell in location.sorted_cells() {
+        let region = location.get(cell).unwrap().meta.region;
+        let theme = match region {
+            0 => floor_theme,
+            _ => *room_themes.entry(region).or_insert_with(|| {
+                if !config.themes.is_empty()
+                    && rng.gen_bool(config.room_theme_chance.clamp(0.0, 1.0))
+                {
+                    rng.gen_range(0..config.themes.len()) as u32
+                } else {
+                    floor_theme
+                }
+            }),
+        };
+
+        let variant = rng.gen();
+        let tile = location.get_mut(cell).unwrap();
+        tile.meta.theme = theme;
+        tile.meta.variant = variant;
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileKind {
+    #[default]
+    Floor,
+    Door,
+    StairsUp,
+    StairsDown,
+    Water,
+    Pit,
+    /// Looks like a wall but can be walked through.
+    SecretPassage,
+}
+
+impl TileKind {
+    pub fn is_passable(self) -> bool {
+        !matches!(self, TileKind::Pit)
+    }
+
+    /// Whether the tile is drawn as solid rock.
+    pub fn is_solid(self) -> bool {
+        matches!(self, TileKind::SecretPassage)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct TileMeta {
+    /// Room or area the tile belongs to, zero for corridors and caves.
+    pub region: u32,
+    /// Index into the theme list, see [`ThemeConfig`](super::theme::ThemeConfig).
+    pub theme: u32,
+    /// Random roll picking the texture variants of the tile.
+    pub variant: u32,
+    pub explored: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Tile {
+    pub kind: TileKind,
+    pub meta: TileMeta,
+}
+
+impl Tile {
+    pub fn new(kind: TileKind) -> Self {
+        Tile {
+            kind,
+            meta: TileMeta::default(),
+        }
+    }
+
+    pub fn with_region(mut self, region: u32) -> Self {
+        self.meta.region = region;
+        self
+    }
+}
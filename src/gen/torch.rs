@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use super::location::WorldCatacomb;
+use super::theme::ThemeConfig;
+use crate::rng::rng::GameRng;
+
+/// Torches are never placed closer than this to each other.
+const TORCH_SPACING: i32 = 3;
+
+/// A torch mounted on the wall in direction `wall` of an open cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WallTorch {
+    pub cell: IVec2,
+    pub wall: IVec2,
+}
+
+/// Rolls a torch for every open cell next to a wall, using the density of the cell's theme.
+pub fn place_torches(location: &mut WorldCatacomb, themes: &ThemeConfig, rng: &mut GameRng) {
+    let mut torches: Vec<WallTorch> = Vec::new();
+
+    for cell in location.sorted_cells() {
+        if location.is_solid(cell) {
+            continue;
+        }
+        let walls = [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y]
+            .into_iter()
+            .filter(|dir| location.is_solid(cell + *dir))
+            .collect::<Vec<_>>();
+        let Some(&wall) = walls.choose(rng) else {
+            continue;
+        };
+
+        let density = location
+            .get(cell)
+            .and_then(|tile| themes.theme(tile.meta.theme))
+            .map_or(0.0, |theme| theme.torch_density);
+        if !rng.gen_bool(density.clamp(0.0, 1.0)) {
+            continue;
+        }
+
+        let crowded = torches
+            .iter()
+            .any(|torch| (torch.cell - cell).abs().element_sum() < TORCH_SPACING);
+        if !crowded {
+            torches.push(WallTorch { cell, wall });
+        }
+    }
+
+    for torch in torches {
+        location.add_torch(torch);
+    }
+}
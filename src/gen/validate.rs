@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::location::WorldCatacomb;
+use super::tile::TileKind;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ValidationConfig {
+    pub min_floor_area: usize,
+    pub max_dead_end_ratio: f32,
+    /// Minimum walking distance to the cell furthest away from the start.
+    pub min_longest_path: u32,
+    /// Enemies only spawn on floor cells at least this far away from the start.
+    pub spawn_distance: u32,
+    pub min_spawn_cells: usize,
+    pub max_attempts: u32,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            min_floor_area: 40,
+            max_dead_end_ratio: 0.25,
+            min_longest_path: 12,
+            spawn_distance: 3,
+            min_spawn_cells: 8,
+            max_attempts: 20,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct LevelMetrics {
+    pub floor_area: usize,
+    pub unreachable: usize,
+    pub dead_ends: usize,
+    pub dead_end_ratio: f32,
+    pub longest_path: u32,
+    pub spawn_cells: usize,
+}
+
+impl LevelMetrics {
+    pub fn measure(location: &WorldCatacomb, start: IVec2, config: &ValidationConfig) -> Self {
+        let distances = location.distances_from(start);
+        let passable = location
+            .cells()
+            .filter(|cell| location.is_passable(*cell))
+            .collect::<Vec<_>>();
+
+        let dead_ends = passable
+            .iter()
+            .filter(|cell| {
+                [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y]
+                    .iter()
+                    .filter(|dir| location.is_passable(**cell + **dir))
+                    .count()
+                    == 1
+            })
+            .count();
+
+        let spawn_cells = distances
+            .iter()
+            .filter(|(cell, distance)| {
+                **distance >= config.spawn_distance
+                    && location.kind(**cell) == Some(TileKind::Floor)
+            })
+            .count();
+
+        LevelMetrics {
+            floor_area: passable.len(),
+            unreachable: passable.len() - distances.len(),
+            dead_ends,
+            dead_end_ratio: dead_ends as f32 / passable.len().max(1) as f32,
+            longest_path: distances.values().copied().max().unwrap_or(0),
+            spawn_cells,
+        }
+    }
+
+    /// Returns every reason for the level to be rejected.
+    pub fn failures(&self, config: &ValidationConfig) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        if self.floor_area < config.min_floor_area {
+            failures.push(format!(
+                "floor area {} is below {}",
+                self.floor_area, config.min_floor_area
+            ));
+        }
+        if self.unreachable > 0 {
+            failures.push(format!("{} cells can't be reached", self.unreachable));
+        }
+        if self.dead_end_ratio > config.max_dead_end_ratio {
+            failures.push(format!(
+                "dead end ratio {:.2} is above {:.2}",
+                self.dead_end_ratio, config.max_dead_end_ratio
+            ));
+        }
+        if self.longest_path < config.min_longest_path {
+            failures.push(format!(
+                "longest path {} is below {}",
+                self.longest_path, config.min_longest_path
+            ));
+        }
+        if self.spawn_cells < config.min_spawn_cells {
+            failures.push(format!(
+                "{} spawn cells is below {}",
+                self.spawn_cells, config.min_spawn_cells
+            ));
+        }
+
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tile::Tile;
+    use super::*;
+
+    #[test]
+    fn measures_corridor_with_detached_cell() {
+        let mut location = WorldCatacomb::default();
+        for x in 0..5 {
+            location.dig(IVec2::new(x, 0));
+        }
+        location.insert(IVec2::new(0, 1), Tile::new(TileKind::Pit));
+        location.dig(IVec2::new(10, 10));
+
+        let config = ValidationConfig {
+            spawn_distance: 3,
+            ..default()
+        };
+        let metrics = LevelMetrics::measure(&location, IVec2::ZERO, &config);
+
+        assert_eq!(metrics.floor_area, 6);
+        assert_eq!(metrics.unreachable, 1);
+        assert_eq!(metrics.dead_ends, 2);
+        assert_eq!(metrics.longest_path, 4);
+        assert_eq!(metrics.spawn_cells, 2);
+        assert!(metrics
+            .failures(&config)
+            .iter()
+            .any(|failure| failure.contains("can't be reached")));
+    }
+}
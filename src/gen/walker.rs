@@ -1,90 +1,179 @@
-use crate::state::GameState;
 use bevy::prelude::*;
-use bevy::{
-    ecs::{
-        component::Component,
-        system::{Query, ResMut},
-    },
-    math::IVec2,
-};
-use rand::{thread_rng, Rng};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
+use super::generator::DungeonGenerator;
 use super::location::WorldCatacomb;
+use crate::rng::rng::GameRng;
+
+const DIRECTIONS: [IVec2; 4] = [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y];
+/// How many times walkers get sent out again to reach `target_floor_cells` before giving up.
+const MAX_RESTARTS: u32 = 1000;
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WalkerConfig {
+    pub walker_count: u32,
+    /// Most walkers sent out at a time, counting every branch, so branching can't dig forever.
+    pub max_walkers: u32,
+    pub min_steps: u32,
+    pub max_steps: u32,
+    /// Chance for a walker to pick a new direction instead of walking straight.
+    pub turn_bias: f64,
+    /// Chance for a walker to spawn a child walker on each step.
+    pub branch_chance: f64,
+    /// Walkers keep being spawned until the catacomb has at least this many floor cells.
+    pub target_floor_cells: usize,
+}
+
+impl Default for WalkerConfig {
+    fn default() -> Self {
+        WalkerConfig {
+            walker_count: 4,
+            max_walkers: 32,
+            min_steps: 3,
+            max_steps: 70,
+            turn_bias: 1.0,
+            branch_chance: 0.0,
+            target_floor_cells: 0,
+        }
+    }
+}
 
-#[derive(Component)]
 pub struct Walker {
     pub current_location: IVec2,
+    direction: IVec2,
     steps_to_live: u32,
 }
 
 impl Walker {
-    pub fn new(start: IVec2) -> Self {
+    pub fn new(start: IVec2, config: &WalkerConfig, rng: &mut impl Rng) -> Self {
+        // A walker needs at least one step, or restarting them can never grow the catacomb.
+        let min_steps = config.min_steps.max(1);
+        let max_steps = config.max_steps.max(min_steps);
         Walker {
             current_location: start,
-            steps_to_live: thread_rng().gen_range(3..=70),
+            direction: *DIRECTIONS.choose(rng).unwrap(),
+            steps_to_live: rng.gen_range(min_steps..=max_steps),
         }
     }
 
-    pub fn walk(&mut self, location: &mut ResMut<WorldCatacomb>) {
+    pub fn walk(
+        &mut self,
+        location: &mut WorldCatacomb,
+        config: &WalkerConfig,
+        rng: &mut impl Rng,
+    ) {
         if self.steps_to_live == 0 {
             return;
         }
-        let is_x = thread_rng().gen_bool(0.5);
-        use rand::prelude::*;
-
-        let offset = IVec2 {
-            x: if is_x {
-                rand::thread_rng().gen_range(-1..=1)
-            } else {
-                0
-            },
-            y: if !is_x {
-                rand::thread_rng().gen_range(-1..=1)
-            } else {
-                0
-            },
-        };
-        self.current_location += offset;
 
-        if !location.0.insert(self.current_location) {
+        if rng.gen_bool(config.turn_bias.clamp(0.0, 1.0)) {
+            self.direction = *DIRECTIONS.choose(rng).unwrap();
+        }
+
+        self.current_location += self.direction;
+
+        if !location.dig(self.current_location) {
             self.steps_to_live -= 1;
         }
     }
-}
 
-pub fn walk_walker_generators(
-    mut q_walkers: Query<&mut Walker>,
-    mut location: ResMut<WorldCatacomb>,
-) {
-    for mut walker in q_walkers.iter_mut() {
-        walker.walk(&mut location);
+    pub fn should_branch(&self, config: &WalkerConfig, rng: &mut impl Rng) -> bool {
+        self.steps_to_live > 0 && rng.gen_bool(config.branch_chance.clamp(0.0, 1.0))
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.steps_to_live > 0
     }
 }
 
-pub fn destroy_walker_generators(q_walkers: Query<(Entity, &Walker)>, mut commands: Commands) {
-    for (entity, walker) in q_walkers.iter() {
-        if walker.steps_to_live > 0 {
-            continue;
+pub struct WalkerGenerator<'a>(pub &'a WalkerConfig);
+
+impl DungeonGenerator for WalkerGenerator<'_> {
+    fn generate(&self, rng: &mut GameRng) -> WorldCatacomb {
+        let config = self.0;
+        let mut location = WorldCatacomb::default();
+        location.dig(IVec2::ZERO);
+
+        let mut walkers = (0..config.walker_count)
+            .map(|_| Walker::new(IVec2::ZERO, config, rng))
+            .collect::<Vec<_>>();
+
+        let mut restarts = 0;
+        loop {
+            let mut spawned = walkers.len() as u32;
+            while !walkers.is_empty() {
+                let mut children = Vec::new();
+                for walker in walkers.iter_mut() {
+                    walker.walk(&mut location, config, rng);
+
+                    if spawned < config.max_walkers && walker.should_branch(config, rng) {
+                        children.push(Walker::new(walker.current_location, config, rng));
+                        spawned += 1;
+                    }
+                }
+                walkers.retain(Walker::is_alive);
+                walkers.extend(children);
+            }
+
+            if location.len() >= config.target_floor_cells {
+                break;
+            }
+            restarts += 1;
+            if restarts > MAX_RESTARTS {
+                warn!(
+                    "Walkers stopped at {} of {} floor cells.",
+                    location.len(),
+                    config.target_floor_cells
+                );
+                break;
+            }
+
+            // Keep digging from existing floor cells until the catacomb is big enough.
+            let start = *location.sorted_cells().choose(rng).unwrap();
+
+            walkers = (0..config.walker_count.max(1))
+                .map(|_| Walker::new(start, config, rng))
+                .collect();
         }
-        commands.entity(entity).despawn()
+
+        location
     }
 }
 
-pub fn setup_walkers(mut commands: Commands) {
-    print!("Setting up random walkers...");
-    for _ in 0..4 {
-        commands.spawn(Walker::new(default()));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(seed: u64, config: &WalkerConfig) -> Vec<IVec2> {
+        WalkerGenerator(config)
+            .generate(&mut GameRng::from_seed(seed))
+            .sorted_cells()
     }
-    println!(" Done!");
-}
 
-pub fn check_walkers(
-    q_walkers: Query<Entity, With<Walker>>,
-    mut state: ResMut<NextState<GameState>>,
-) {
-    if !q_walkers.is_empty() {
-        return;
+    #[test]
+    fn same_seed_digs_same_catacomb() {
+        let config = WalkerConfig {
+            branch_chance: 0.1,
+            target_floor_cells: 200,
+            ..default()
+        };
+
+        assert_eq!(generate(42, &config), generate(42, &config));
+        assert_ne!(generate(42, &config), generate(43, &config));
+    }
+
+    #[test]
+    fn walkers_without_steps_still_dig() {
+        let config = WalkerConfig {
+            min_steps: 0,
+            max_steps: 0,
+            target_floor_cells: 50,
+            ..default()
+        };
+
+        assert!(generate(1, &config).len() >= 50);
     }
-    info!("Done generating...");
-    state.set(GameState::Game);
 }
@@ -0,0 +1,192 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    TurnLeft,
+    TurnRight,
+    Forward,
+    Back,
+    StrafeLeft,
+    StrafeRight,
+    Attack,
+    Wait,
+    /// Waits until healed or something shows up.
+    Rest,
+    Interact,
+    AutoExplore,
+    /// Walks to the cell under the cursor.
+    TravelTo,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 12] = [
+        InputAction::TurnLeft,
+        InputAction::TurnRight,
+        InputAction::Forward,
+        InputAction::Back,
+        InputAction::StrafeLeft,
+        InputAction::StrafeRight,
+        InputAction::Attack,
+        InputAction::Wait,
+        InputAction::Rest,
+        InputAction::Interact,
+        InputAction::AutoExplore,
+        InputAction::TravelTo,
+    ];
+
+    /// Whether the action goes through the player's movement queue. The others start
+    /// something that runs on its own, like travel or rest, or are read straight away.
+    pub fn is_queued(self) -> bool {
+        !matches!(
+            self,
+            InputAction::Interact
+                | InputAction::Rest
+                | InputAction::AutoExplore
+                | InputAction::TravelTo
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+/// Which keys, mouse buttons and gamepad buttons trigger each action.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct InputBindings {
+    pub actions: HashMap<InputAction, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use Binding::*;
+
+        InputBindings {
+            actions: HashMap::from_iter([
+                (
+                    InputAction::TurnLeft,
+                    vec![
+                        Key(KeyCode::KeyA),
+                        Key(KeyCode::ArrowLeft),
+                        Gamepad(GamepadButton::DPadLeft),
+                    ],
+                ),
+                (
+                    InputAction::TurnRight,
+                    vec![
+                        Key(KeyCode::KeyD),
+                        Key(KeyCode::ArrowRight),
+                        Gamepad(GamepadButton::DPadRight),
+                    ],
+                ),
+                (
+                    InputAction::Forward,
+                    vec![
+                        Key(KeyCode::KeyW),
+                        Key(KeyCode::ArrowUp),
+                        Mouse(MouseButton::Left),
+                        Gamepad(GamepadButton::DPadUp),
+                    ],
+                ),
+                (
+                    InputAction::Back,
+                    vec![
+                        Key(KeyCode::KeyS),
+                        Key(KeyCode::ArrowDown),
+                        Gamepad(GamepadButton::DPadDown),
+                    ],
+                ),
+                (
+                    InputAction::StrafeLeft,
+                    vec![Key(KeyCode::KeyQ), Gamepad(GamepadButton::LeftTrigger)],
+                ),
+                (
+                    InputAction::StrafeRight,
+                    vec![Key(KeyCode::KeyE), Gamepad(GamepadButton::RightTrigger)],
+                ),
+                (
+                    InputAction::Attack,
+                    vec![Key(KeyCode::Space), Gamepad(GamepadButton::South)],
+                ),
+                (
+                    InputAction::Wait,
+                    vec![Key(KeyCode::Period), Gamepad(GamepadButton::West)],
+                ),
+                (
+                    InputAction::Rest,
+                    vec![Key(KeyCode::KeyR), Gamepad(GamepadButton::East)],
+                ),
+                (
+                    InputAction::Interact,
+                    vec![Key(KeyCode::KeyF), Gamepad(GamepadButton::North)],
+                ),
+                (
+                    InputAction::AutoExplore,
+                    vec![Key(KeyCode::KeyX), Gamepad(GamepadButton::Select)],
+                ),
+                (InputAction::TravelTo, vec![Mouse(MouseButton::Right)]),
+            ]),
+        }
+    }
+}
+
+impl InputBindings {
+    /// Gives actions left out of a config file their default bindings.
+    pub fn with_defaults(mut self) -> Self {
+        for (action, bindings) in InputBindings::default().actions {
+            self.actions.entry(action).or_insert(bindings);
+        }
+        self
+    }
+}
+
+/// Actions started by any of their bindings this frame.
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    just_pressed: Vec<InputAction>,
+}
+
+impl ActionState {
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn press(&mut self, action: InputAction) {
+        self.just_pressed.push(action);
+    }
+
+    pub fn clear(&mut self) {
+        self.just_pressed.clear();
+    }
+}
+
+pub fn update_actions(
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    q_gamepads: Query<&Gamepad>,
+    mut state: ResMut<ActionState>,
+) {
+    let just_pressed = |binding: &Binding| match binding {
+        Binding::Key(key) => keyboard.just_pressed(*key),
+        Binding::Mouse(button) => mouse.just_pressed(*button),
+        Binding::Gamepad(button) => q_gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(*button)),
+    };
+
+    state.clear();
+    for action in InputAction::ALL {
+        let Some(bindings) = bindings.actions.get(&action) else {
+            continue;
+        };
+        if bindings.iter().any(just_pressed) {
+            state.press(action);
+        }
+    }
+}
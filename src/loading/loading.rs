@@ -1,58 +1,105 @@
 use bevy::prelude::*;
 
-use crate::state::GameState;
+use crate::{
+    combat::weapon::{WeaponDef, CULTIST_WEAPON, PLAYER_WEAPON},
+    gen::{
+        ascii::{AsciiMap, CustomMap, CustomMapPath},
+        prefab::PrefabFolder,
+        theme::ThemeConfig,
+    },
+    state::GameState,
+};
 
 #[derive(Resource, Default)]
 pub struct LoadingAssets(Vec<UntypedHandle>);
 
-pub fn setup_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn setup_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    custom_map_path: Option<Res<CustomMapPath>>,
+    themes: Res<ThemeConfig>,
+) {
     let mut loading_assets = LoadingAssets::default();
 
-    loading_assets
-        .0
-        .push(asset_server.load_untyped("sprites/doomguy.png").untyped());
+    if let Some(path) = custom_map_path {
+        let handle = asset_server.load::<AsciiMap>(path.0.clone());
+        loading_assets.0.push(handle.clone().untyped());
+        commands.insert_resource(CustomMap(handle));
+    }
 
-    loading_assets
-        .0
-        .push(asset_server.load_untyped("sprites/cultist.png").untyped());
+    let prefabs = asset_server.load_folder("prefabs");
+    loading_assets.0.push(prefabs.clone().untyped());
+    commands.insert_resource(PrefabFolder(prefabs));
 
     loading_assets
         .0
-        .push(asset_server.load_untyped("textures/wall.png").untyped());
+        .push(asset_server.load_untyped("sprites/doomguy.png").untyped());
 
     loading_assets
         .0
-        .push(asset_server.load_untyped("textures/floor.png").untyped());
+        .push(asset_server.load_untyped("sprites/cultist.png").untyped());
 
     loading_assets
         .0
         .push(asset_server.load_untyped("music/catacombs.mid").untyped());
 
-    loading_assets.0.push(
-        asset_server
-            .load_untyped("textures/wall_emission.png")
-            .untyped(),
-    );
+    for weapon in [PLAYER_WEAPON, CULTIST_WEAPON] {
+        loading_assets
+            .0
+            .push(asset_server.load::<WeaponDef>(weapon).untyped());
+    }
+
+    for texture in themes.textures() {
+        loading_assets
+            .0
+            .push(asset_server.load::<Image>(texture).untyped());
+    }
 
     commands.insert_resource(loading_assets);
 }
 
 pub fn check_assets_ready(
     server: Res<AssetServer>,
-    loading: Res<LoadingAssets>,
+    mut loading: ResMut<LoadingAssets>,
+    custom_map: Option<Res<CustomMap>>,
+    prefabs: Res<PrefabFolder>,
+    mut commands: Commands,
     mut state: ResMut<NextState<GameState>>,
 ) {
-    use bevy::asset::LoadState;
+    use bevy::asset::{LoadState, RecursiveDependencyLoadState};
+
+    if let Some(custom_map) = &custom_map {
+        if let Some(LoadState::Failed(err)) = server.get_load_state(custom_map.0.id()) {
+            error!("Failed to load custom map: {err}. Generating a floor instead.");
+            loading
+                .0
+                .retain(|asset| asset.id() != custom_map.0.id().untyped());
+            commands.remove_resource::<CustomMap>();
+            return;
+        }
+    }
+
+    // A broken vault shouldn't keep the game from starting, the rest still get used.
+    if let Some(RecursiveDependencyLoadState::Failed(err)) =
+        server.get_recursive_dependency_load_state(prefabs.0.id())
+    {
+        warn!("Some prefabs failed to load: {err}.");
+        loading
+            .0
+            .retain(|asset| asset.id() != prefabs.0.id().untyped());
+    }
 
     if loading
         .0
         .iter()
-        .all(|asset| match server.get_load_state(asset.id()).unwrap() {
-            LoadState::Loaded => true,
-            _ => false,
-        })
+        .all(|asset| server.is_loaded_with_dependencies(asset.id()))
     {
         println!("loaded");
-        state.set(GameState::Generating);
+        // Hand-authored maps don't need to be generated.
+        state.set(if custom_map.is_some() {
+            GameState::Game
+        } else {
+            GameState::Generating
+        });
     }
 }
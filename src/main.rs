@@ -1,3 +1,4 @@
+use std::fs;
 use std::fs::File;
 use std::io::Cursor;
 use std::path::PathBuf;
@@ -10,8 +11,12 @@ use bevy_sprite3d::Sprite3dPlugin;
 mod audio;
 mod characters;
 mod combat;
+mod dungeon;
 mod gen;
+mod input;
 mod loading;
+mod replay;
+mod rng;
 mod room;
 mod state;
 mod tick;
@@ -20,6 +25,8 @@ mod utils;
 mod visuals;
 
 use bevy::diagnostic::*;
+use bevy::input::InputSystem;
+use bevy::log::tracing_subscriber;
 use bevy::window::*;
 use bevy_inspector_egui::quick::*;
 use characters::enemy::enemy::enemies_find_player;
@@ -28,13 +35,17 @@ use characters::enemy::enemy::setup_enemies;
 use characters::enemy::enemy::setup_enemy_atlas;
 use characters::location::update_character_sprite_positions;
 use characters::location::WorldLocation;
+use characters::occupancy::*;
 use characters::player::camera::*;
 use characters::player::control::*;
 use characters::player::player::setup_player;
+use characters::player::rest::*;
+use characters::player::travel::*;
 use clap::arg;
 use clap::command;
 use clap::value_parser;
 use clap::ArgMatches;
+use clap::Command;
 use combat::combat::check_enemy_combat;
 use combat::combat::check_player_combat;
 use combat::combat::damage_enemy;
@@ -44,28 +55,245 @@ use combat::combat::update_combat;
 use combat::combat::CombatEvent;
 use combat::combat::CombatState;
 use combat::combat::DamagedEvent;
+use combat::regeneration::regenerate;
+use combat::weapon::{WeaponDef, WeaponLoader};
+use dungeon::dungeon::*;
+use gen::ascii::*;
+use gen::export::*;
+use gen::generator::*;
 use gen::location::*;
+use gen::prefab::*;
+use gen::theme::ThemeConfig;
+use gen::validate::LevelMetrics;
 use gen::walker::*;
+use input::input::*;
 use loading::loading::*;
+use replay::replay::*;
+use rng::rng::*;
+use room::chunk::*;
 use room::mesh::*;
 use state::GameState;
 use std::io::Read;
-use tick::tick::TickEvent;
+use tick::tick::*;
 use ui::tint::damage_screen;
 use ui::tint::destroy_tints;
+use utils::config::load_config;
 use visuals::animation::animate_sprite;
 use visuals::animation::Animations;
 use visuals::billboard::update_billboards;
+use visuals::torch::*;
 
 fn cli() -> ArgMatches {
     command!()
         .arg(
-            arg!([soundfont]
+            arg!(soundfont:
                 -s --soundfont <FILE> "Sets custom soundfont before startup"
             )
             .required(false)
             .value_parser(value_parser!(PathBuf)),
         )
+        .arg(
+            arg!(seed:
+                --seed <SEED> "Sets the seed used for generation and every other random roll"
+            )
+            .required(false)
+            .global(true)
+            .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            arg!(walker_config:
+                --"walker-config" <FILE> "Loads walker generator parameters from a RON file"
+            )
+            .default_value("config/walker.ron")
+            .global(true)
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(walkers:
+                --walkers <COUNT> "Sets how many walkers start digging the catacomb"
+            )
+            .required(false)
+            .global(true)
+            .value_parser(value_parser!(u32)),
+        )
+        .arg(
+            arg!(walker_steps:
+                --"walker-steps" <STEPS> "Sets the minimum and maximum steps a walker lives"
+            )
+            .required(false)
+            .global(true)
+            .num_args(2)
+            .value_parser(value_parser!(u32)),
+        )
+        .arg(
+            arg!(turn_bias:
+                --"turn-bias" <CHANCE> "Sets the chance for a walker to change direction"
+            )
+            .required(false)
+            .global(true)
+            .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            arg!(branch_chance:
+                --"branch-chance" <CHANCE> "Sets the chance for a walker to spawn a child walker"
+            )
+            .required(false)
+            .global(true)
+            .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            arg!(floor_cells:
+                --"floor-cells" <COUNT> "Sets the minimum amount of floor cells to generate"
+            )
+            .required(false)
+            .global(true)
+            .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            arg!(generator_config:
+                --"generator-config" <FILE> "Loads per-floor generator settings from a RON file"
+            )
+            .required(false)
+            .global(true)
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(generators:
+                --generators <KINDS> "Sets the generator for each floor (walker, bsp or cave)"
+            )
+            .required(false)
+            .global(true)
+            .value_delimiter(',')
+            .value_parser(value_parser!(GeneratorKind)),
+        )
+        .arg(
+            arg!(theme_config:
+                --"theme-config" <FILE> "Loads texture themes and the floors they are used on from a RON file"
+            )
+            .default_value("config/themes.ron")
+            .global(true)
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(render_config:
+                --"render-config" <FILE> "Loads the internal render resolution from a RON file"
+            )
+            .default_value("config/render.ron")
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(resolution:
+                --resolution <SIZE> "Sets the width and height the world is rendered at before scaling"
+            )
+            .required(false)
+            .num_args(2)
+            .value_parser(value_parser!(u32)),
+        )
+        .arg(
+            arg!(input_config:
+                --"input-config" <FILE> "Loads key, mouse and gamepad bindings from a RON file"
+            )
+            .default_value("config/input.ron")
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(movement_config:
+                --"movement-config" <FILE> "Loads the input buffer size and movement speed from a RON file"
+            )
+            .default_value("config/movement.ron")
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(lerp_speed:
+                --"lerp-speed" <SPEED> "Sets how quickly the camera glides between cells"
+            )
+            .required(false)
+            .value_parser(positive_f32),
+        )
+        .arg(
+            arg!(enemy_config:
+                --"enemy-config" <FILE> "Loads the stats of each enemy type from a RON file"
+            )
+            .default_value("config/enemies.ron")
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(mode:
+                --mode <MODE> "Sets whether time passes with each action or on its own (turn-based or real-time)"
+            )
+            .default_value("turn-based")
+            .value_parser(value_parser!(GameMode)),
+        )
+        .arg(
+            arg!(tick_interval:
+                --"tick-interval" <SECONDS> "Sets the time between ticks in real-time mode"
+            )
+            .default_value("0.5")
+            .value_parser(positive_f32),
+        )
+        .arg(
+            arg!(record:
+                --record <FILE> "Records the seed and every player action to a file (turn-based mode only)"
+            )
+            .required(false)
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(replay:
+                --replay <FILE> "Plays back a recorded run, using its seed and actions"
+            )
+            .required(false)
+            .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            arg!(--"player-torch" "Gives the player a torch that lights up their surroundings")
+                .id("player_torch"),
+        )
+        .arg(
+            arg!(map:
+                --map <FILE> "Starts in a hand-authored map, relative to the assets folder"
+            )
+            .required(false),
+        )
+        .arg(
+            arg!(geometry:
+                --geometry <MODE> "Sets how world geometry is built (chunked or per-cell)"
+            )
+            .default_value("chunked")
+            .value_parser(value_parser!(GeometryMode)),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Generates a single floor without starting the game and prints it")
+                .arg(
+                    arg!(floor:
+                        --floor <DEPTH> "Sets which floor of the dungeon to generate"
+                    )
+                    .default_value("0")
+                    .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    arg!(format:
+                        -f --format <FORMAT> "Sets the output format (ascii or json)"
+                    )
+                    .default_value("ascii")
+                    .value_parser(value_parser!(OutputFormat)),
+                )
+                .arg(
+                    arg!(output:
+                        -o --output <FILE> "Writes the floor to a file instead of stdout"
+                    )
+                    .required(false)
+                    .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(prefabs:
+                        --prefabs <DIR> "Sets the folder prefab vaults are read from"
+                    )
+                    .default_value("assets/prefabs")
+                    .value_parser(value_parser!(PathBuf)),
+                ),
+        )
         .get_matches()
 }
 
@@ -108,17 +336,235 @@ fn try_open_soundfont(path: Option<PathBuf>) -> Vec<u8> {
     }
 }
 
+fn walker_config(matches: &ArgMatches) -> WalkerConfig {
+    let mut config =
+        load_config::<WalkerConfig>(matches.get_one::<PathBuf>("walker_config").unwrap());
+
+    if let Some(walkers) = matches.get_one::<u32>("walkers") {
+        config.walker_count = *walkers;
+    }
+    if let Some(mut steps) = matches.get_many::<u32>("walker_steps") {
+        config.min_steps = *steps.next().unwrap();
+        config.max_steps = *steps.next().unwrap();
+    }
+    if let Some(turn_bias) = matches.get_one::<f64>("turn_bias") {
+        config.turn_bias = *turn_bias;
+    }
+    if let Some(branch_chance) = matches.get_one::<f64>("branch_chance") {
+        config.branch_chance = *branch_chance;
+    }
+    if let Some(floor_cells) = matches.get_one::<usize>("floor_cells") {
+        config.target_floor_cells = *floor_cells;
+    }
+
+    config
+}
+
+fn generator_config(matches: &ArgMatches) -> GeneratorConfig {
+    let mut config = match matches.get_one::<PathBuf>("generator_config") {
+        Some(path) => load_config::<GeneratorConfig>(path),
+        None => GeneratorConfig::default(),
+    };
+
+    if let Some(generators) = matches.get_many::<GeneratorKind>("generators") {
+        config.floors = generators.copied().collect();
+    }
+
+    config
+}
+
+fn render_resolution(matches: &ArgMatches) -> RenderResolution {
+    let mut resolution =
+        load_config::<RenderResolution>(matches.get_one::<PathBuf>("render_config").unwrap());
+
+    if let Some(mut size) = matches.get_many::<u32>("resolution") {
+        resolution.width = *size.next().unwrap();
+        resolution.height = *size.next().unwrap();
+    }
+
+    // A zero-size render target can't be created.
+    resolution.width = resolution.width.max(1);
+    resolution.height = resolution.height.max(1);
+    resolution
+}
+
+fn movement_config(matches: &ArgMatches) -> MovementConfig {
+    let mut config =
+        load_config::<MovementConfig>(matches.get_one::<PathBuf>("movement_config").unwrap());
+
+    if let Some(lerp_speed) = matches.get_one::<f32>("lerp_speed") {
+        config.lerp_speed = *lerp_speed;
+    }
+    // Moves only run once the camera settles, which it never does without speed.
+    if config.lerp_speed <= 0.0 {
+        warn!(
+            "Lerp speed {} isn't above zero, using the default.",
+            config.lerp_speed
+        );
+        config.lerp_speed = MovementConfig::default().lerp_speed;
+    }
+    config.buffer_size = config.buffer_size.max(1);
+
+    config
+}
+
+fn positive_f32(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(value) if value > 0.0 => Ok(value),
+        Ok(_) => Err("must be above zero".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn run_config(matches: &ArgMatches) -> RunConfig {
+    RunConfig {
+        seed: seed(matches),
+        mode: *matches.get_one::<GameMode>("mode").unwrap(),
+        tick_interval: *matches.get_one::<f32>("tick_interval").unwrap(),
+        walker: walker_config(matches),
+        generator: generator_config(matches),
+        themes: load_config(matches.get_one::<PathBuf>("theme_config").unwrap()),
+        enemies: load_config(matches.get_one::<PathBuf>("enemy_config").unwrap()),
+        movement: movement_config(matches),
+        map: matches.get_one::<String>("map").cloned(),
+    }
+}
+
+fn seed(matches: &ArgMatches) -> u64 {
+    matches
+        .get_one::<u64>("seed")
+        .copied()
+        .unwrap_or_else(rand::random)
+}
+
+/// Runs only the generator for one floor, without a window or any Bevy app.
+fn generate_headless(matches: &ArgMatches) {
+    // Without the game's LogPlugin, logs need a subscriber of their own. Stdout is for the floor.
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    let seed = seed(matches);
+    let depth = *matches.get_one::<usize>("floor").unwrap();
+    let walker_config = walker_config(matches);
+    let generator_config = generator_config(matches);
+    let prefabs = PrefabLibrary::load_dir(matches.get_one::<PathBuf>("prefabs").unwrap());
+    let themes = load_config::<ThemeConfig>(matches.get_one::<PathBuf>("theme_config").unwrap());
+
+    info!("Using seed {seed}");
+    let location = generate_floor(
+        depth,
+        &generator_config,
+        &walker_config,
+        &prefabs.0,
+        &themes,
+        &mut GameRng::from_seed(seed).for_floor(depth),
+    );
+
+    let output = match matches.get_one::<OutputFormat>("format").unwrap() {
+        OutputFormat::Ascii => to_ascii(&location),
+        OutputFormat::Json => {
+            let metrics =
+                LevelMetrics::measure(&location, IVec2::ZERO, &generator_config.validation);
+            let floor = ExportedFloor::new(
+                &location,
+                seed,
+                depth,
+                generator_config.kind_for_floor(depth),
+                metrics,
+            );
+            serde_json::to_string_pretty(&floor).unwrap() + "\n"
+        }
+    };
+
+    match matches.get_one::<PathBuf>("output") {
+        Some(path) => {
+            if let Err(err) = fs::write(path, output) {
+                eprintln!("Failed to write \"{}\": {}.", path.display(), err);
+                std::process::exit(1);
+            }
+        }
+        None => print!("{output}"),
+    }
+}
+
 fn main() {
     let matches = cli();
 
+    if let Some(matches) = matches.subcommand_matches("generate") {
+        generate_headless(matches);
+        return;
+    }
+
     let soundfont_path = matches.get_one::<PathBuf>("soundfont").cloned();
     dbg!(soundfont_path.clone());
     let sf2_vec = try_open_soundfont(soundfont_path).clone();
 
-    App::new()
-        .add_plugins(LogDiagnosticsPlugin::default())
+    let replay = matches
+        .get_one::<PathBuf>("replay")
+        .map(|path| match Recording::load(path) {
+            Ok(recording) => recording,
+            Err(err) => {
+                eprintln!("Failed to load replay \"{}\": {}.", path.display(), err);
+                std::process::exit(1);
+            }
+        });
+    // A replay brings the settings it was recorded with, whatever the other flags say.
+    let run_config = match &replay {
+        Some(recording) => recording.config.clone(),
+        None => run_config(&matches),
+    };
+
+    let render_resolution = render_resolution(&matches);
+    let bindings =
+        load_config::<InputBindings>(matches.get_one::<PathBuf>("input_config").unwrap())
+            .with_defaults();
+    let geometry_mode = *matches.get_one::<GeometryMode>("geometry").unwrap();
+
+    let mut app = App::new();
+
+    let record = matches.get_one::<PathBuf>("record");
+    // Real-time ticks follow the wall clock, so a replay of them would play out differently.
+    if run_config.mode == GameMode::RealTime && (record.is_some() || replay.is_some()) {
+        eprintln!("Recording and replaying only work in turn-based mode.");
+        std::process::exit(1);
+    }
+    if let Some(path) = record {
+        match Recorder::create(path.clone(), &run_config) {
+            Ok(recorder) => app.insert_resource(recorder),
+            Err(err) => {
+                eprintln!(
+                    "Failed to create recording \"{}\": {}.",
+                    path.display(),
+                    err
+                );
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(recording) = replay {
+        app.insert_resource(Replay::new(recording));
+    }
+
+    let RunConfig {
+        seed,
+        mode,
+        tick_interval,
+        walker,
+        generator,
+        themes,
+        enemies,
+        movement,
+        map,
+    } = run_config;
+    if let Some(path) = map {
+        app.insert_resource(CustomMapPath(path));
+    }
+
+    app.add_plugins(LogDiagnosticsPlugin::default())
         // .add_plugins(FrameTimeDiagnosticsPlugin::default())
         .add_systems(PreStartup, setup_loading)
+        .add_systems(Startup, log_seed)
         // .add_plugins(PlayerPlugin)
         .add_plugins(
             DefaultPlugins
@@ -140,62 +586,125 @@ fn main() {
         .add_plugins(Sprite3dPlugin)
         .add_plugins(WorldInspectorPlugin::new())
         .register_type::<WorldLocation>()
+        .register_type::<Speed>()
+        .register_type::<Energy>()
+        .init_asset::<AsciiMap>()
+        .init_asset_loader::<AsciiMapLoader>()
+        .init_asset::<Prefab>()
+        .init_asset_loader::<PrefabLoader>()
+        .init_asset::<WeaponDef>()
+        .init_asset_loader::<WeaponLoader>()
         .add_event::<TickEvent>()
+        .add_event::<TurnEvent>()
+        .add_event::<RecordedAction>()
+        .init_resource::<TurnClock>()
+        .insert_resource(mode)
+        .insert_resource(RealTimeTicks::new(tick_interval))
         .add_event::<CombatEvent>()
         .add_event::<DamagedEvent>()
         .insert_resource(WorldCatacomb::default())
+        .insert_resource(Dungeon::default())
+        .init_resource::<Occupancy>()
+        .insert_resource(PrefabLibrary::default())
+        .insert_resource(geometry_mode)
+        .insert_resource(render_resolution)
+        .insert_resource(themes)
+        .insert_resource(bindings)
+        .init_resource::<ActionState>()
+        .insert_resource(movement)
+        .insert_resource(enemies)
+        .init_resource::<MovementQueue>()
+        .init_resource::<Travel>()
+        .init_resource::<Rest>()
+        .insert_resource(PlayerTorch(matches.get_flag("player_torch")))
+        .init_resource::<MaterialCache>()
         .insert_resource(Animations::default())
+        .insert_resource(GameRng::from_seed(seed))
+        .insert_resource(walker)
+        .insert_resource(generator)
         .insert_resource(CombatState {
             cooldown: Timer::from_seconds(0.5, TimerMode::Once),
             opponent: None,
-            is_player_turn: false,
+            player_attacking: false,
         })
         .init_state::<GameState>()
         .add_systems(
             Update,
             check_assets_ready.run_if(in_state(GameState::Loading)),
         )
-        .add_systems(OnExit(GameState::Loading), setup_enemy_atlas)
-        .add_systems(OnEnter(GameState::Generating), setup_walkers)
-        .add_systems(PostStartup, (setup_player, setup_camera, spawn_fog).chain())
         .add_systems(
-            Update,
+            OnExit(GameState::Loading),
+            (
+                setup_enemy_atlas,
+                setup_background_music,
+                load_custom_map,
+                collect_prefabs,
+            ),
+        )
+        .add_systems(OnEnter(GameState::Generating), enter_floor)
+        .add_systems(
+            PreUpdate,
             (
-                walk_walker_generators,
-                destroy_walker_generators,
-                check_walkers,
+                update_actions.run_if(not(resource_exists::<Replay>)),
+                replay_actions.run_if(resource_exists::<Replay>),
             )
-                .run_if(in_state(GameState::Generating)),
+                .after(InputSystem),
+        )
+        .add_systems(Last, record_actions.run_if(resource_exists::<Recorder>))
+        .add_systems(
+            PostStartup,
+            (setup_player, setup_camera, spawn_fog, setup_player_torch).chain(),
         )
         .add_systems(
             Update,
             (
                 (
                     sync_camera,
-                    move_player,
-                    move_enemies,
-                    enemies_find_player,
-                    update_combat,
-                    (damage_enemy, damage_player).chain(),
+                    // Everything that acts on turns runs in one fixed order, so replays match.
+                    (
+                        queue_actions,
+                        start_travel,
+                        rest_player,
+                        travel_player,
+                        move_player,
+                        tick_real_time.run_if(resource_equals(GameMode::RealTime)),
+                        schedule_turns,
+                        enemies_find_player,
+                        move_enemies,
+                        check_player_combat,
+                        damage_enemy,
+                        check_enemy_combat,
+                        damage_player,
+                        update_combat,
+                        regenerate,
+                    )
+                        .chain(),
                     destroy_tints,
                     despawn_dead_enemies,
-                    check_player_combat,
-                    check_enemy_combat,
                     damage_screen,
                     animate_sprite,
+                    explore_cells,
+                    update_fog,
+                    use_stairs,
+                    rebuild_dirty_chunks.run_if(resource_equals(GeometryMode::Chunked)),
                 )
                     .run_if(in_state(GameState::Game)),
-                (update_character_sprite_positions, update_billboards),
+                (
+                    update_character_sprite_positions,
+                    update_billboards,
+                    flicker_lights,
+                    scale_render_output,
+                ),
             ),
         )
         .add_systems(
-            OnExit(GameState::Generating),
-            ((
-                setup_rooms,
-                setup_walls,
-                setup_background_music,
-                setup_enemies,
-            ),),
+            OnEnter(GameState::Game),
+            (
+                (setup_rooms, setup_walls).run_if(resource_equals(GeometryMode::PerCell)),
+                setup_chunks.run_if(resource_equals(GeometryMode::Chunked)),
+                setup_torches,
+                (place_player, setup_enemies).chain(),
+            ),
         )
         .run();
 }
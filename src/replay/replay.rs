@@ -0,0 +1,406 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    characters::{
+        enemy::enemy::EnemyConfig,
+        player::{
+            control::{MovementConfig, MovementQueue},
+            rest::Rest,
+            travel::{Destination, Travel},
+        },
+    },
+    gen::{generator::GeneratorConfig, theme::ThemeConfig, walker::WalkerConfig},
+    input::input::{ActionState, InputAction},
+    state::GameState,
+    tick::tick::{GameMode, TurnClock},
+};
+
+/// A player action and the turn it was taken on. `cell` is the destination of `TravelTo`.
+#[derive(Event, Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct RecordedAction {
+    pub turn: u64,
+    pub action: InputAction,
+    pub cell: Option<IVec2>,
+}
+
+/// Every setting that shapes the world or how it reacts, so a replay can't drift from the run.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunConfig {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub tick_interval: f32,
+    pub walker: WalkerConfig,
+    pub generator: GeneratorConfig,
+    pub themes: ThemeConfig,
+    pub enemies: EnemyConfig,
+    pub movement: MovementConfig,
+    /// Hand-authored map the run starts in instead of a generated floor.
+    pub map: Option<String>,
+}
+
+/// Everything needed to play a run back: its settings and the player's actions in order.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Recording {
+    pub config: RunConfig,
+    pub actions: Vec<RecordedAction>,
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(std::io::Error),
+    Encoding(bincode::Error),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Io(err) => write!(f, "couldn't access recording: {err}"),
+            RecordingError::Encoding(err) => write!(f, "invalid recording: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl From<std::io::Error> for RecordingError {
+    fn from(err: std::io::Error) -> Self {
+        RecordingError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for RecordingError {
+    fn from(err: bincode::Error) -> Self {
+        RecordingError::Encoding(err)
+    }
+}
+
+/// Writes `value` behind its length, so a recording can grow one action at a time.
+fn write_record(writer: &mut impl Write, value: &impl Serialize) -> Result<(), RecordingError> {
+    let bytes = bincode::serialize(value)?;
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Reads the record at the front of `bytes`, or `None` once they run out. A record cut short,
+/// like the last one written before a crash, counts as the end too.
+fn read_record<T: DeserializeOwned>(bytes: &mut &[u8]) -> Result<Option<T>, RecordingError> {
+    let Some((len, rest)) = bytes.split_first_chunk::<4>() else {
+        return Ok(None);
+    };
+    let len = u32::from_le_bytes(*len) as usize;
+    if rest.len() < len {
+        return Ok(None);
+    }
+    let value = bincode::deserialize(&rest[..len])?;
+    *bytes = &rest[len..];
+    Ok(Some(value))
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self, RecordingError> {
+        let file = fs::read(path)?;
+        let mut bytes = file.as_slice();
+        let Some(config) = read_record(&mut bytes)? else {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        };
+        let mut actions = Vec::new();
+        while let Some(action) = read_record(&mut bytes)? {
+            actions.push(action);
+        }
+        Ok(Recording { config, actions })
+    }
+}
+
+/// Appends every [`RecordedAction`] to a file as it happens.
+#[derive(Resource)]
+pub struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: PathBuf, config: &RunConfig) -> Result<Self, RecordingError> {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        write_record(&mut writer, config)?;
+        writer.flush()?;
+        Ok(Recorder { path, writer })
+    }
+
+    fn append(&mut self, action: &RecordedAction) -> Result<(), RecordingError> {
+        write_record(&mut self.writer, action)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+pub fn record_actions(mut recorder: ResMut<Recorder>, mut ev_action: EventReader<RecordedAction>) {
+    for action in ev_action.read() {
+        // Flushed right away so a crash still leaves a replay of what led up to it.
+        if let Err(err) = recorder.append(action) {
+            warn!(
+                "Failed to save recording \"{}\": {}.",
+                recorder.path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Actions of a recording still waiting for their turn. Live input is ignored until it runs out.
+#[derive(Resource)]
+pub struct Replay(VecDeque<RecordedAction>);
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Replay(recording.actions.into())
+    }
+}
+
+/// Presses the recorded actions in place of [`update_actions`](crate::input::input::update_actions),
+/// one per frame, once the game has reached the turn they were taken on.
+pub fn replay_actions(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut state: ResMut<ActionState>,
+    mut travel: ResMut<Travel>,
+    mut rest: ResMut<Rest>,
+    mut ev_action: EventWriter<RecordedAction>,
+    clock: Res<TurnClock>,
+    queue: Res<MovementQueue>,
+    config: Res<MovementConfig>,
+    game_state: Res<State<GameState>>,
+) {
+    // Cleared outside the game too, so a press doesn't linger through a floor change.
+    state.clear();
+    if *game_state.get() != GameState::Game {
+        return;
+    }
+
+    let Some(next) = replay.0.front().copied() else {
+        info!("Replay finished.");
+        commands.remove_resource::<Replay>();
+        return;
+    };
+    if next.turn > clock.turn() {
+        return;
+    }
+    // Only recorded actions made it into the queue, so wait for room instead of dropping one.
+    if next.action.is_queued() && queue.len() >= config.buffer_size {
+        return;
+    }
+    replay.0.pop_front();
+
+    match (next.action, next.cell) {
+        (InputAction::TravelTo, Some(cell)) => {
+            travel.start(Destination::Cell(cell));
+            rest.stop();
+            // Everything else gets recorded again by the systems the press goes through.
+            ev_action.send(next);
+        }
+        (action, _) => state.press(action),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        characters::{
+            facing::Facing,
+            location::WorldLocation,
+            occupancy::Occupancy,
+            player::{
+                camera::RenderResolution,
+                control::{move_player, queue_actions},
+                player::Player,
+                rest::rest_player,
+                travel::{start_travel, travel_player},
+            },
+        },
+        combat::combat::{CombatState, Health},
+        dungeon::dungeon::explore_cells,
+        gen::{ascii::AsciiMap, generator::GeneratorKind},
+        tick::tick::TickEvent,
+        utils::config::load_config,
+    };
+
+    #[test]
+    fn recording_round_trips_through_bincode() {
+        let recording = Recording {
+            config: RunConfig {
+                seed: 7,
+                mode: GameMode::TurnBased,
+                tick_interval: 0.25,
+                walker: default(),
+                generator: GeneratorConfig {
+                    floors: vec![GeneratorKind::Bsp, GeneratorKind::Cave],
+                    ..default()
+                },
+                themes: load_config(
+                    &Path::new(env!("CARGO_MANIFEST_DIR")).join("config/themes.ron"),
+                ),
+                enemies: default(),
+                movement: default(),
+                map: Some("maps/crypt.map".to_string()),
+            },
+            actions: vec![
+                RecordedAction {
+                    turn: 0,
+                    action: InputAction::Forward,
+                    cell: None,
+                },
+                RecordedAction {
+                    turn: 3,
+                    action: InputAction::TravelTo,
+                    cell: Some(IVec2::new(4, -2)),
+                },
+            ],
+        };
+
+        let path = temp_path("round-trip");
+        let mut recorder = Recorder::create(path.clone(), &recording.config).unwrap();
+        for action in recording.actions.iter() {
+            recorder.append(action).unwrap();
+        }
+        drop(recorder);
+        let loaded = Recording::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(format!("{:?}", loaded.unwrap()), format!("{recording:?}"));
+    }
+
+    #[test]
+    fn replay_reaches_the_same_turn() {
+        let path = temp_path("replay");
+        let mut app = test_app();
+        app.insert_resource(Recorder::create(path.clone(), &test_config()).unwrap());
+
+        press(&mut app, InputAction::Rest);
+        for _ in 0..3 {
+            app.update();
+        }
+        // Rest spends a tick every frame, the press has to land on the turn before this one's.
+        press(&mut app, InputAction::Forward);
+        press(&mut app, InputAction::AutoExplore);
+        for _ in 0..40 {
+            app.update();
+        }
+        let recorded = finish(&mut app);
+        app.world_mut().remove_resource::<Recorder>();
+
+        let recording = Recording::load(&path);
+        fs::remove_file(&path).unwrap();
+        let recording = recording.unwrap();
+        let actions = recording
+            .actions
+            .iter()
+            .map(|action| (action.turn, action.action))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            [
+                (0, InputAction::Rest),
+                (4, InputAction::Forward),
+                (5, InputAction::AutoExplore)
+            ]
+        );
+        let mut app = test_app();
+        app.insert_resource(Replay::new(recording));
+        for _ in 0..60 {
+            app.update();
+        }
+
+        assert!(!app.world().contains_resource::<Replay>());
+        assert_eq!(finish(&mut app), recorded);
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{name}-{}.bin", std::process::id()))
+    }
+
+    fn test_config() -> RunConfig {
+        RunConfig {
+            seed: 0,
+            mode: GameMode::TurnBased,
+            tick_interval: 0.5,
+            walker: default(),
+            generator: default(),
+            themes: default(),
+            enemies: default(),
+            movement: default(),
+            map: None,
+        }
+    }
+
+    /// The player's turn-driven systems on a corridor, without a window or any enemies.
+    fn test_app() -> App {
+        let map = AsciiMap::parse("###########\n#@........#\n###########").unwrap();
+
+        let mut app = App::new();
+        app.add_event::<TickEvent>()
+            .add_event::<RecordedAction>()
+            .insert_resource(map.0)
+            .insert_resource(GameMode::TurnBased)
+            .insert_resource(State::new(GameState::Game))
+            .insert_resource(CombatState {
+                cooldown: Timer::from_seconds(0.5, TimerMode::Once),
+                opponent: None,
+                player_attacking: false,
+            })
+            .init_resource::<TurnClock>()
+            .init_resource::<ActionState>()
+            .init_resource::<MovementConfig>()
+            .init_resource::<MovementQueue>()
+            .init_resource::<Travel>()
+            .init_resource::<Rest>()
+            .init_resource::<Occupancy>()
+            .init_resource::<RenderResolution>()
+            .add_systems(PreUpdate, replay_actions.run_if(resource_exists::<Replay>))
+            .add_systems(
+                Update,
+                (
+                    queue_actions,
+                    start_travel,
+                    rest_player,
+                    travel_player,
+                    move_player,
+                    explore_cells,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, record_actions.run_if(resource_exists::<Recorder>));
+        // Hurt, so resting doesn't stop on its own.
+        app.world_mut().spawn((
+            Player,
+            WorldLocation::new(IVec2::ZERO, Facing::East),
+            Health(1),
+        ));
+        app
+    }
+
+    /// Presses `action` for a single frame, the way input would.
+    fn press(app: &mut App, action: InputAction) {
+        app.world_mut().resource_mut::<ActionState>().press(action);
+        app.update();
+        app.world_mut().resource_mut::<ActionState>().clear();
+    }
+
+    fn finish(app: &mut App) -> (u64, IVec2) {
+        let location = app
+            .world_mut()
+            .query_filtered::<&WorldLocation, With<Player>>()
+            .single(app.world())
+            .get_location();
+        (app.world().resource::<TurnClock>().turn(), location)
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// A separate generator for each floor, so its layout only depends on the seed.
+    pub fn for_floor(&self, depth: usize) -> Self {
+        GameRng::from_seed(
+            self.seed
+                .wrapping_add((depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        )
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+pub fn log_seed(rng: Res<GameRng>) {
+    info!("Using seed {}", rng.seed());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roll(seed: u64) -> Vec<u64> {
+        let mut rng = GameRng::from_seed(seed);
+        (0..8).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn same_seed_rolls_same_numbers() {
+        assert_eq!(roll(42), roll(42));
+        assert_ne!(roll(42), roll(43));
+    }
+}
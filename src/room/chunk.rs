@@ -0,0 +1,139 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    dungeon::dungeon::FloorEntity,
+    gen::{
+        location::{WorldCatacomb, CHUNK_SIZE},
+        theme::Surface,
+        tile::TileKind,
+    },
+    utils::utils::convert_ivec2_to_vec3_plane,
+};
+
+use super::mesh::{
+    generate_floor_mesh, generate_wall_mesh, wall_faces, wall_mask, WorldMaterials, F32_ROOM_SIZE,
+};
+
+#[derive(Component)]
+pub struct Chunk(pub IVec2);
+
+fn append(target: &mut Option<Mesh>, mesh: &Mesh, translation: Vec3) {
+    let mesh = mesh.clone().translated_by(translation);
+    match target {
+        Some(target) => target.merge(&mesh),
+        None => *target = Some(mesh),
+    }
+}
+
+/// Merges the floors, ceilings and walls of every cell in a chunk, one mesh per material.
+fn build_chunk(
+    location: &WorldCatacomb,
+    materials: &mut WorldMaterials,
+    chunk: IVec2,
+) -> HashMap<Handle<StandardMaterial>, Mesh> {
+    let floor_mesh = generate_floor_mesh();
+    // Faces can get different materials, so walls are merged one side at a time.
+    let face_meshes = [1, 2, 4, 8].map(generate_wall_mesh);
+    let mut meshes = HashMap::<_, Option<Mesh>>::new();
+
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            let cell = chunk * CHUNK_SIZE + IVec2::new(x, y);
+            let translation = convert_ivec2_to_vec3_plane(cell) * F32_ROOM_SIZE;
+
+            if !location.is_solid(cell) {
+                let tile = location.get(cell).unwrap();
+                if tile.kind != TileKind::Pit {
+                    if let Some(material) = materials.get(tile, Surface::Floor) {
+                        append(
+                            meshes.entry(material).or_default(),
+                            &floor_mesh,
+                            translation,
+                        );
+                    }
+                }
+                if let Some(material) = materials.get(tile, Surface::Ceiling) {
+                    append(
+                        meshes.entry(material).or_default(),
+                        &floor_mesh,
+                        translation.with_y(F32_ROOM_SIZE),
+                    );
+                }
+                continue;
+            }
+
+            for (face, neighbour) in wall_faces(wall_mask(location, cell), cell) {
+                let Some(material) = location
+                    .get(neighbour)
+                    .and_then(|tile| materials.get(tile, Surface::Wall))
+                else {
+                    continue;
+                };
+                append(
+                    meshes.entry(material).or_default(),
+                    &face_meshes[face.trailing_zeros() as usize],
+                    translation.with_y(F32_ROOM_SIZE / 2.0),
+                );
+            }
+        }
+    }
+
+    meshes
+        .into_iter()
+        .filter_map(|(material, mesh)| Some((material, mesh?)))
+        .collect()
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    assets: &mut Assets<Mesh>,
+    materials: &mut WorldMaterials,
+    location: &WorldCatacomb,
+    chunk: IVec2,
+) {
+    for (material, mesh) in build_chunk(location, materials, chunk) {
+        commands.spawn((
+            Chunk(chunk),
+            FloorEntity,
+            Mesh3d(assets.add(mesh)),
+            MeshMaterial3d(material),
+        ));
+    }
+}
+
+pub fn setup_chunks(
+    mut location: ResMut<WorldCatacomb>,
+    mut commands: Commands,
+    mut assets: ResMut<Assets<Mesh>>,
+    mut materials: WorldMaterials,
+) {
+    location.take_dirty_chunks();
+    let chunks = location.chunks();
+    info!("Building {} chunks...", chunks.len());
+
+    for chunk in chunks {
+        spawn_chunk(&mut commands, &mut assets, &mut materials, &location, chunk);
+    }
+}
+
+pub fn rebuild_dirty_chunks(
+    mut location: ResMut<WorldCatacomb>,
+    mut commands: Commands,
+    mut assets: ResMut<Assets<Mesh>>,
+    mut materials: WorldMaterials,
+    q_chunks: Query<(Entity, &Chunk)>,
+) {
+    if !location.has_dirty_chunks() {
+        return;
+    }
+    let dirty = location.take_dirty_chunks();
+
+    for (entity, chunk) in q_chunks.iter() {
+        if dirty.contains(&chunk.0) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for chunk in dirty {
+        spawn_chunk(&mut commands, &mut assets, &mut materials, &location, chunk);
+    }
+}
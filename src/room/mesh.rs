@@ -1,141 +1,156 @@
-use bevy::{asset::RenderAssetUsages, prelude::*, render::mesh::Indices};
+use std::str::FromStr;
 
-use crate::{gen::location::WorldCatacomb, utils::utils::convert_ivec2_to_vec3_plane};
+use bevy::{
+    asset::RenderAssetUsages,
+    ecs::system::SystemParam,
+    prelude::*,
+    render::mesh::Indices,
+    utils::{HashMap, HashSet},
+};
 
-fn generate_floor_mesh(assets: &mut ResMut<Assets<Mesh>>) -> Handle<Mesh> {
+use crate::{
+    dungeon::dungeon::FloorEntity,
+    gen::{
+        location::WorldCatacomb,
+        theme::{Surface, TextureVariant, ThemeConfig},
+        tile::{Tile, TileKind},
+    },
+    utils::utils::convert_ivec2_to_vec3_plane,
+};
+
+pub fn generate_floor_mesh() -> Mesh {
     use bevy::render::mesh::*;
 
-    assets.add(Cuboid::new(F32_ROOM_SIZE, 0.0, F32_ROOM_SIZE).mesh())
+    Cuboid::new(F32_ROOM_SIZE, 0.0, F32_ROOM_SIZE)
+        .mesh()
+        .build()
 }
 
-fn generate_wall_mesh(assets: &mut ResMut<Assets<Mesh>>) -> Handle<Mesh> {
-    let size = F32_ROOM_SIZE * 0.5;
+/// A side of a wall cube: the neighbour it faces, its corners, UVs and normal.
+struct WallFace {
+    neighbour: IVec2,
+    positions: [[f32; 3]; 4],
+    uvs: [[f32; 2]; 4],
+    normal: [f32; 3],
+    indices: [u32; 6],
+}
 
-    let mesh = Mesh::new(
-        bevy::render::mesh::PrimitiveTopology::TriangleList,
-        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
-    )
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        vec![
-            // top (facing towards +y)
-            [-size, size, -size], // vertex with index 0
-            [size, size, -size],  // vertex with index 1
-            [size, size, size],   // etc. until 23
-            [-size, size, size],
-            // bottom   (-y)
-            [-size, -size, -size],
-            [size, -size, -size],
-            [size, -size, size],
-            [-size, -size, size],
-            // right    (+x)
-            [size, -size, -size],
-            [size, -size, size],
-            [size, size, size], // This vertex is at the same position as vertex with index 2, but they'll have different UV and normal
-            [size, size, -size],
-            // left     (-x)
-            [-size, -size, -size],
-            [-size, -size, size],
-            [-size, size, size],
-            [-size, size, -size],
-            // back     (+z)
-            [-size, -size, size],
-            [-size, size, size],
-            [size, size, size],
-            [size, -size, size],
-            // forward  (-z)
-            [-size, -size, -size],
-            [-size, size, -size],
-            [size, size, -size],
-            [size, -size, -size],
+const WALL_SIZE: f32 = F32_ROOM_SIZE * 0.5;
+
+// Grid +y points towards world -z, see `convert_ivec2_to_vec3_plane`.
+const WALL_FACES: [WallFace; 4] = [
+    // right    (+x)
+    WallFace {
+        neighbour: IVec2::X,
+        positions: [
+            [WALL_SIZE, -WALL_SIZE, -WALL_SIZE],
+            [WALL_SIZE, -WALL_SIZE, WALL_SIZE],
+            [WALL_SIZE, WALL_SIZE, WALL_SIZE],
+            [WALL_SIZE, WALL_SIZE, -WALL_SIZE],
         ],
-    )
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_UV_0,
-        vec![
-            // Assigning the UV coords for the top side.
-            [0.0, 0.0],
-            [1.0, 0.0],
-            [1.0, 1.0],
-            [0.0, 1.0],
-            // Assigning the UV coords for the bottom side.
-            [0.0, 0.0],
-            [1.0, 0.0],
-            [1.0, 1.0],
-            [0.0, 1.0],
-            ////////
-            // Assigning the UV coords for the right side.
-            [1.0, 1.0],
-            [0.0, 1.0],
-            [0.0, 0.0],
-            [1.0, 0.0], // Done
-            // Assigning the UV coords for the left side.
-            [1.0, 1.0],
-            [0.0, 1.0],
-            [0.0, 0.0],
-            [1.0, 0.0], // Done
-            // Assigning the UV coords for the back side.
-            [0.0, 1.0],
-            [0.0, 0.0],
-            [1.0, 0.0],
-            [1.0, 1.0], // Done
-            // Assigning the UV coords for the forward side.
-            [0.0, 1.0],
-            [0.0, 0.0],
-            [1.0, 0.0],
-            [1.0, 1.0],
+        uvs: [[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]],
+        normal: [1.0, 0.0, 0.0],
+        indices: [0, 3, 1, 1, 3, 2],
+    },
+    // left     (-x)
+    WallFace {
+        neighbour: IVec2::NEG_X,
+        positions: [
+            [-WALL_SIZE, -WALL_SIZE, -WALL_SIZE],
+            [-WALL_SIZE, -WALL_SIZE, WALL_SIZE],
+            [-WALL_SIZE, WALL_SIZE, WALL_SIZE],
+            [-WALL_SIZE, WALL_SIZE, -WALL_SIZE],
         ],
-    )
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_NORMAL,
-        vec![
-            // Normals for the top side (towards +y)
-            [0.0, 1.0, 0.0],
-            [0.0, 1.0, 0.0],
-            [0.0, 1.0, 0.0],
-            [0.0, 1.0, 0.0],
-            // Normals for the bottom side (towards -y)
-            [0.0, -1.0, 0.0],
-            [0.0, -1.0, 0.0],
-            [0.0, -1.0, 0.0],
-            [0.0, -1.0, 0.0],
-            // Normals for the right side (towards +x)
-            [1.0, 0.0, 0.0],
-            [1.0, 0.0, 0.0],
-            [1.0, 0.0, 0.0],
-            [1.0, 0.0, 0.0],
-            // Normals for the left side (towards -x)
-            [-1.0, 0.0, 0.0],
-            [-1.0, 0.0, 0.0],
-            [-1.0, 0.0, 0.0],
-            [-1.0, 0.0, 0.0],
-            // Normals for the back side (towards +z)
-            [0.0, 0.0, 1.0],
-            [0.0, 0.0, 1.0],
-            [0.0, 0.0, 1.0],
-            [0.0, 0.0, 1.0],
-            // Normals for the forward side (towards -z)
-            [0.0, 0.0, -1.0],
-            [0.0, 0.0, -1.0],
-            [0.0, 0.0, -1.0],
-            [0.0, 0.0, -1.0],
+        uvs: [[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]],
+        normal: [-1.0, 0.0, 0.0],
+        indices: [0, 1, 3, 1, 2, 3],
+    },
+    // back     (+z)
+    WallFace {
+        neighbour: IVec2::NEG_Y,
+        positions: [
+            [-WALL_SIZE, -WALL_SIZE, WALL_SIZE],
+            [-WALL_SIZE, WALL_SIZE, WALL_SIZE],
+            [WALL_SIZE, WALL_SIZE, WALL_SIZE],
+            [WALL_SIZE, -WALL_SIZE, WALL_SIZE],
+        ],
+        uvs: [[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [1.0, 1.0]],
+        normal: [0.0, 0.0, 1.0],
+        indices: [0, 3, 1, 1, 3, 2],
+    },
+    // forward  (-z)
+    WallFace {
+        neighbour: IVec2::Y,
+        positions: [
+            [-WALL_SIZE, -WALL_SIZE, -WALL_SIZE],
+            [-WALL_SIZE, WALL_SIZE, -WALL_SIZE],
+            [WALL_SIZE, WALL_SIZE, -WALL_SIZE],
+            [WALL_SIZE, -WALL_SIZE, -WALL_SIZE],
         ],
+        uvs: [[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [1.0, 1.0]],
+        normal: [0.0, 0.0, -1.0],
+        indices: [0, 1, 3, 1, 2, 3],
+    },
+];
+
+/// One bit per entry of `WALL_FACES`, set when that neighbour is open and the face can be seen.
+pub fn wall_mask(location: &WorldCatacomb, cell: IVec2) -> u8 {
+    if !location.is_solid(cell) {
+        return 0;
+    }
+    WALL_FACES
+        .iter()
+        .enumerate()
+        .filter(|(_, face)| !location.is_solid(cell + face.neighbour))
+        .fold(0, |mask, (i, _)| mask | 1 << i)
+}
+
+/// Splits a [`wall_mask`] into single faces, each with the open cell it faces.
+pub fn wall_faces(mask: u8, cell: IVec2) -> impl Iterator<Item = (u8, IVec2)> {
+    WALL_FACES
+        .iter()
+        .enumerate()
+        .filter(move |(i, _)| mask & 1 << i != 0)
+        .map(move |(i, face)| (1 << i, cell + face.neighbour))
+}
+
+/// Builds only the sides of a wall cell picked by `mask`, see [`wall_mask`].
+pub fn generate_wall_mesh(mask: u8) -> Mesh {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
+
+    for (i, face) in WALL_FACES.iter().enumerate() {
+        if mask & 1 << i == 0 {
+            continue;
+        }
+        let offset = positions.len() as u32;
+        positions.extend(face.positions);
+        uvs.extend(face.uvs);
+        normals.extend([face.normal; 4]);
+        indices.extend(face.indices.map(|index| index + offset));
+    }
+
+    Mesh::new(
+        bevy::render::mesh::PrimitiveTopology::TriangleList,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
     )
-    .with_inserted_indices(Indices::U32(vec![
-        0, 3, 1, 1, 3, 2, // triangles making up the top (+y) facing side.
-        4, 5, 7, 5, 6, 7, // bottom (-y)
-        8, 11, 9, 9, 11, 10, // right (+x)
-        12, 13, 15, 13, 14, 15, // left (-x)
-        16, 19, 17, 17, 19, 18, // back (+z)
-        20, 21, 23, 21, 22, 23, // forward (-z)
-    ]));
-
-    assets.add(mesh)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_indices(Indices::U32(indices))
 }
 
-fn new_material(texture: Handle<Image>, emission: Option<Handle<Image>>) -> StandardMaterial {
+fn new_material(variant: &TextureVariant, textures: &AssetServer) -> StandardMaterial {
+    let emission = variant
+        .emission
+        .as_ref()
+        .map(|emission| textures.load(emission));
+
     StandardMaterial {
-        base_color_texture: Some(texture),
+        base_color: Color::srgb_from_array(variant.tint),
+        base_color_texture: Some(textures.load(&variant.texture)),
         emissive: if emission.is_some() {
             LinearRgba::WHITE
         } else {
@@ -150,37 +165,104 @@ fn new_material(texture: Handle<Image>, emission: Option<Handle<Image>>) -> Stan
 const ROOM_SIZE: i32 = 2;
 pub const F32_ROOM_SIZE: f32 = ROOM_SIZE as f32;
 
+#[derive(Resource, Default)]
+pub struct MaterialCache(HashMap<(u32, Surface, usize), Handle<StandardMaterial>>);
+
+/// Hands out one material per theme texture, shared by every tile that uses it.
+#[derive(SystemParam)]
+pub struct WorldMaterials<'w> {
+    cache: ResMut<'w, MaterialCache>,
+    themes: Res<'w, ThemeConfig>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    textures: Res<'w, AssetServer>,
+}
+
+impl WorldMaterials<'_> {
+    pub fn get(&mut self, tile: &Tile, surface: Surface) -> Option<Handle<StandardMaterial>> {
+        let theme_index = if self.themes.theme(tile.meta.theme).is_some() {
+            tile.meta.theme
+        } else {
+            0
+        };
+        let theme = self.themes.theme(theme_index)?;
+        let variant = theme.pick(surface, tile.meta.variant)?;
+
+        let handle = self
+            .cache
+            .0
+            .entry((theme_index, surface, variant))
+            .or_insert_with(|| {
+                self.materials.add(new_material(
+                    &theme.variants(surface)[variant],
+                    &self.textures,
+                ))
+            });
+        Some(handle.clone())
+    }
+}
+
+/// How world geometry gets built: one merged mesh per chunk, or an entity per cell.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeometryMode {
+    #[default]
+    Chunked,
+    PerCell,
+}
+
+impl FromStr for GeometryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chunked" => Ok(GeometryMode::Chunked),
+            "per-cell" => Ok(GeometryMode::PerCell),
+            _ => Err(format!(
+                "unknown geometry mode \"{s}\", expected chunked or per-cell"
+            )),
+        }
+    }
+}
+
 pub fn setup_rooms(
     location: Res<WorldCatacomb>,
     mut commands: Commands,
     mut assets: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    textures: Res<AssetServer>,
+    mut materials: WorldMaterials,
 ) {
     println!("Setting up rooms");
-    for loc in location.0.iter() {
+    let mesh = assets.add(generate_floor_mesh());
+    for (loc, tile) in location.iter() {
+        if tile.kind.is_solid() {
+            continue;
+        }
         // Floor
-        let mesh = generate_floor_mesh(&mut assets);
-        commands.spawn((
-            Mesh3d(mesh),
-            MeshMaterial3d(materials.add(new_material(textures.load("textures/floor.png"), None))),
-            Transform {
-                translation: convert_ivec2_to_vec3_plane(*loc) * F32_ROOM_SIZE,
-                ..default()
-            },
-        ));
+        if tile.kind != TileKind::Pit {
+            if let Some(material) = materials.get(tile, Surface::Floor) {
+                commands.spawn((
+                    FloorEntity,
+                    Mesh3d(mesh.clone()),
+                    MeshMaterial3d(material),
+                    Transform {
+                        translation: convert_ivec2_to_vec3_plane(loc) * F32_ROOM_SIZE,
+                        ..default()
+                    },
+                ));
+            }
+        }
         // Ceiling
-        let mesh = generate_floor_mesh(&mut assets);
-        let mut translation = convert_ivec2_to_vec3_plane(*loc) * F32_ROOM_SIZE;
+        let mut translation = convert_ivec2_to_vec3_plane(loc) * F32_ROOM_SIZE;
         translation.y = F32_ROOM_SIZE;
-        commands.spawn((
-            Mesh3d(mesh),
-            MeshMaterial3d(materials.add(new_material(textures.load("textures/floor.png"), None))),
-            Transform {
-                translation,
-                ..default()
-            },
-        ));
+        if let Some(material) = materials.get(tile, Surface::Ceiling) {
+            commands.spawn((
+                FloorEntity,
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material),
+                Transform {
+                    translation,
+                    ..default()
+                },
+            ));
+        }
     }
 }
 
@@ -188,32 +270,46 @@ pub fn setup_walls(
     location: Res<WorldCatacomb>,
     mut commands: Commands,
     mut assets: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    textures: Res<AssetServer>,
+    mut materials: WorldMaterials,
 ) {
     println!("Setting up walls");
+    let mut meshes = HashMap::new();
 
-    for loc in location.0.iter() {
-        for i in -1..2 {
-            for j in -1..2 {
-                let loc = *loc + IVec2::from_array([i, j]);
-                if !location.0.contains(&loc) {
-                    let mesh = generate_wall_mesh(&mut assets);
-                    let mut translation = convert_ivec2_to_vec3_plane(loc) * F32_ROOM_SIZE;
-                    translation.y = F32_ROOM_SIZE / 2.0;
-                    commands.spawn((
-                        Mesh3d(mesh),
-                        MeshMaterial3d(materials.add(new_material(
-                            textures.load("textures/wall.png"),
-                            Some(textures.load("textures/wall_emission.png")),
-                        ))),
-                        Transform {
-                            translation,
-                            ..default()
-                        },
-                    ));
-                }
+    let walls = location
+        .cells()
+        .flat_map(|cell| [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y].map(|dir| cell + dir))
+        .collect::<HashSet<_>>();
+
+    for loc in walls {
+        let mut translation = convert_ivec2_to_vec3_plane(loc) * F32_ROOM_SIZE;
+        translation.y = F32_ROOM_SIZE / 2.0;
+
+        // Each side takes its texture from the theme of the cell in front of it,
+        // sides sharing a material are drawn as one mesh.
+        let mut masks = HashMap::<_, u8>::new();
+        for (face, neighbour) in wall_faces(wall_mask(&location, loc), loc) {
+            if let Some(material) = location
+                .get(neighbour)
+                .and_then(|tile| materials.get(tile, Surface::Wall))
+            {
+                *masks.entry(material).or_default() |= face;
             }
         }
+
+        for (material, mask) in masks {
+            let mesh = meshes
+                .entry(mask)
+                .or_insert_with(|| assets.add(generate_wall_mesh(mask)));
+
+            commands.spawn((
+                FloorEntity,
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material),
+                Transform {
+                    translation,
+                    ..default()
+                },
+            ));
+        }
     }
 }
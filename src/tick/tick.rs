@@ -1,4 +1,209 @@
-use bevy::prelude::*;
+use std::str::FromStr;
 
-#[derive(Event)]
-pub struct TickEvent;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+/// Energy an actor spends to take one turn.
+pub const TURN_COST: u32 = 100;
+
+/// One step of game time, numbered from the start of the run.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TickEvent {
+    pub turn: u64,
+}
+
+/// An actor has gathered enough energy to act.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TurnEvent {
+    pub actor: Entity,
+    pub turn: u64,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct TurnClock {
+    turn: u64,
+}
+
+impl TurnClock {
+    pub fn turn(&self) -> u64 {
+        self.turn
+    }
+
+    /// Starts the next turn and returns the tick announcing it.
+    pub fn tick(&mut self) -> TickEvent {
+        self.turn += 1;
+        TickEvent { turn: self.turn }
+    }
+}
+
+/// Whether time passes with the player's actions or on its own.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    TurnBased,
+    RealTime,
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "turn-based" => Ok(GameMode::TurnBased),
+            "real-time" => Ok(GameMode::RealTime),
+            _ => Err(format!(
+                "unknown game mode \"{s}\", expected turn-based or real-time"
+            )),
+        }
+    }
+}
+
+/// Sends a tick for every player action in turn-based mode. In real-time mode only
+/// [`tick_real_time`] moves time along, so player actions don't speed up the world.
+#[derive(SystemParam)]
+pub struct Ticks<'w> {
+    mode: Res<'w, GameMode>,
+    clock: ResMut<'w, TurnClock>,
+    ev_tick: EventWriter<'w, TickEvent>,
+}
+
+impl Ticks<'_> {
+    pub fn turn(&self) -> u64 {
+        self.clock.turn()
+    }
+
+    pub fn spend(&mut self) {
+        if *self.mode == GameMode::TurnBased {
+            self.ev_tick.send(self.clock.tick());
+        }
+    }
+}
+
+/// Interval between ticks in real-time mode.
+#[derive(Resource)]
+pub struct RealTimeTicks(pub Timer);
+
+impl RealTimeTicks {
+    pub fn new(seconds: f32) -> Self {
+        RealTimeTicks(Timer::from_seconds(seconds, TimerMode::Repeating))
+    }
+}
+
+pub fn tick_real_time(
+    time: Res<Time>,
+    mut timer: ResMut<RealTimeTicks>,
+    mut clock: ResMut<TurnClock>,
+    mut ev_tick: EventWriter<TickEvent>,
+) {
+    timer.0.tick(time.delta());
+    for _ in 0..timer.0.times_finished_this_tick() {
+        ev_tick.send(clock.tick());
+    }
+}
+
+/// Energy gained every tick. At [`TURN_COST`] an actor acts once per tick, like the player.
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+#[require(Energy)]
+pub struct Speed(pub u32);
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed(TURN_COST)
+    }
+}
+
+#[derive(Component, Reflect, Debug, Default, Clone, Copy)]
+pub struct Energy(pub u32);
+
+/// Hands out turns to every actor with a [`Speed`], as often as their energy allows.
+pub fn schedule_turns(
+    mut ev_tick: EventReader<TickEvent>,
+    mut ev_turn: EventWriter<TurnEvent>,
+    mut q_actors: Query<(Entity, &Speed, &mut Energy)>,
+) {
+    for tick in ev_tick.read() {
+        // Entity order keeps the turn order the same from one run to the next.
+        let mut actors = q_actors.iter_mut().collect::<Vec<_>>();
+        actors.sort_by_key(|(entity, _, _)| *entity);
+
+        for (actor, speed, energy) in actors.iter_mut() {
+            energy.0 += speed.0;
+            while energy.0 >= TURN_COST {
+                energy.0 -= TURN_COST;
+                ev_turn.send(TurnEvent {
+                    actor: *actor,
+                    turn: tick.turn,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn turns(app: &mut App) -> Vec<(Entity, u64)> {
+        app.world_mut()
+            .resource_mut::<Events<TurnEvent>>()
+            .drain()
+            .map(|turn| (turn.actor, turn.turn))
+            .collect()
+    }
+
+    #[test]
+    fn actors_take_turns_by_speed_in_entity_order() {
+        let mut app = App::new();
+        app.add_event::<TickEvent>()
+            .add_event::<TurnEvent>()
+            .add_systems(Update, schedule_turns);
+        let slow = app.world_mut().spawn(Speed(50)).id();
+        let fast = app.world_mut().spawn(Speed(200)).id();
+        let normal = app.world_mut().spawn(Speed::default()).id();
+
+        app.world_mut().send_event(TickEvent { turn: 1 });
+        app.update();
+        assert_eq!(turns(&mut app), [(fast, 1), (fast, 1), (normal, 1)]);
+
+        app.world_mut().send_event(TickEvent { turn: 2 });
+        app.update();
+        assert_eq!(
+            turns(&mut app),
+            [(slow, 2), (fast, 2), (fast, 2), (normal, 2)]
+        );
+        assert_eq!(app.world().get::<Energy>(slow).unwrap().0, 0);
+    }
+
+    #[test]
+    fn real_time_ticks_follow_the_clock_not_the_player() {
+        let mut app = App::new();
+        app.add_event::<TickEvent>()
+            .insert_resource(GameMode::RealTime)
+            .insert_resource(RealTimeTicks::new(0.5))
+            .init_resource::<TurnClock>()
+            .init_resource::<Time>()
+            .add_systems(
+                Update,
+                (
+                    |mut ticks: Ticks| ticks.spend(),
+                    tick_real_time.run_if(resource_equals(GameMode::RealTime)),
+                )
+                    .chain(),
+            );
+
+        app.update();
+        assert_eq!(app.world().resource::<TurnClock>().turn, 0);
+
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(1200));
+        app.update();
+        assert_eq!(app.world().resource::<TurnClock>().turn, 2);
+
+        *app.world_mut().resource_mut::<GameMode>() = GameMode::TurnBased;
+        app.update();
+        assert_eq!(app.world().resource::<TurnClock>().turn, 3);
+    }
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+
+pub fn load_config<T: DeserializeOwned + Default>(path: &Path) -> T {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!(
+                "Failed to open config at path \"{}\": {}.\nUsing defaults",
+                path.display(),
+                err
+            );
+            return T::default();
+        }
+    };
+
+    match ron::from_str(&contents) {
+        Ok(config) => {
+            info!("Loaded config \"{}\".", path.display());
+            config
+        }
+        Err(err) => {
+            warn!(
+                "Failed to parse config at path \"{}\": {}.\nUsing defaults",
+                path.display(),
+                err
+            );
+            T::default()
+        }
+    }
+}
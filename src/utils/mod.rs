@@ -1 +1,2 @@
-pub mod utils;
+pub mod config;
+pub mod utils;
@@ -8,3 +8,11 @@ pub fn convert_ivec2_to_vec3_plane(v: IVec2) -> Vec3 {
     }
     .as_vec3()
 }
+
+/// Inverse of [`convert_ivec2_to_vec3_plane`], rounding to the closest cell.
+pub fn convert_vec3_plane_to_ivec2(v: Vec3) -> IVec2 {
+    IVec2 {
+        x: v.x.round() as i32,
+        y: (-v.z).round() as i32,
+    }
+}
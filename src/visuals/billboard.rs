@@ -1,17 +1,34 @@
 use bevy::prelude::*;
 
+use crate::characters::{
+    location::WorldLocation,
+    player::{control::MovementConfig, player::Player},
+};
+
 #[derive(Component)]
 #[require(Transform)]
 pub struct Billboard;
 
+/// Turns billboards along with the camera, so they keep facing the player's view.
 pub fn update_billboards(
-    q_cameras: Query<(&Transform, &Camera3d), Without<Billboard>>,
-    mut q_billboards: Query<&mut Transform, With<Billboard>>,
+    q_player: Query<&WorldLocation, With<Player>>,
+    mut q_billboards: Query<(&mut Transform, Ref<Billboard>)>,
+    config: Res<MovementConfig>,
+    time: Res<Time>,
 ) {
-    let (cam, _) = q_cameras.single();
-    let forward = cam.forward().as_vec3();
+    let Ok(player_location) = q_player.get_single() else {
+        return;
+    };
+    let rotation = player_location.get_facing().rotation();
 
-    for mut billboard_transform in q_billboards.iter_mut() {
-        billboard_transform.look_to(forward, Vec3::Y);
+    for (mut billboard_transform, billboard) in q_billboards.iter_mut() {
+        // New billboards start out facing the camera instead of turning into place.
+        billboard_transform.rotation = if billboard.is_added() {
+            rotation
+        } else {
+            billboard_transform
+                .rotation
+                .lerp(rotation, time.delta_secs() * config.lerp_speed)
+        };
     }
 }
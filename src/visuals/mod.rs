@@ -1,2 +1,3 @@
 pub mod animation;
 pub mod billboard;
+pub mod torch;
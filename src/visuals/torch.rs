@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use crate::{
+    characters::player::player::Player, dungeon::dungeon::FloorEntity,
+    gen::location::WorldCatacomb, room::mesh::F32_ROOM_SIZE,
+    utils::utils::convert_ivec2_to_vec3_plane,
+};
+
+const TORCH_COLOR: Color = Color::srgb(1.0, 0.6, 0.3);
+const TORCH_INTENSITY: f32 = 40_000.0;
+const TORCH_RANGE: f32 = 8.0;
+const TORCH_HEIGHT: f32 = 1.4;
+
+#[derive(Resource, Default)]
+pub struct PlayerTorch(pub bool);
+
+/// Makes a point light flicker around its base intensity.
+#[derive(Component)]
+#[require(PointLight)]
+pub struct Flicker {
+    pub intensity: f32,
+    /// Offsets the flicker so neighbouring torches don't pulse in sync.
+    pub phase: f32,
+}
+
+fn torch_light(intensity: f32) -> PointLight {
+    PointLight {
+        color: TORCH_COLOR,
+        intensity,
+        range: TORCH_RANGE,
+        shadows_enabled: false,
+        ..default()
+    }
+}
+
+pub fn setup_torches(mut commands: Commands, location: Res<WorldCatacomb>) {
+    for torch in location.torches() {
+        let mut translation = (convert_ivec2_to_vec3_plane(torch.cell)
+            + convert_ivec2_to_vec3_plane(torch.wall) * 0.4)
+            * F32_ROOM_SIZE;
+        translation.y = TORCH_HEIGHT;
+
+        commands.spawn((
+            FloorEntity,
+            torch_light(TORCH_INTENSITY),
+            Flicker {
+                intensity: TORCH_INTENSITY,
+                phase: (torch.cell.x * 7 + torch.cell.y * 13) as f32,
+            },
+            Transform::from_translation(translation),
+        ));
+    }
+}
+
+pub fn setup_player_torch(
+    mut commands: Commands,
+    player_torch: Res<PlayerTorch>,
+    q_player: Query<Entity, With<Player>>,
+) {
+    if !player_torch.0 {
+        return;
+    }
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+
+    // Held a bit to the side and below the eyes so it doesn't light the view flatly.
+    commands.entity(player).with_children(|parent| {
+        parent.spawn((
+            torch_light(TORCH_INTENSITY * 0.5),
+            Flicker {
+                intensity: TORCH_INTENSITY * 0.5,
+                phase: 0.0,
+            },
+            Transform::from_xyz(0.3, -0.3, 0.0),
+        ));
+    });
+}
+
+pub fn flicker_lights(time: Res<Time>, mut q_lights: Query<(&mut PointLight, &Flicker)>) {
+    let t = time.elapsed_secs();
+    for (mut light, flicker) in q_lights.iter_mut() {
+        let t = t + flicker.phase;
+        let noise = (t * 7.3).sin() * 0.5 + (t * 13.1).sin() * 0.3 + (t * 23.7).sin() * 0.2;
+        light.intensity = flicker.intensity * (1.0 + noise * 0.15);
+    }
+}